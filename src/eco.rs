@@ -0,0 +1,71 @@
+use crate::Game;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// A small table of common openings: the ECO code, the name, and the moves leading to it in the form taken by `Game::take_turn`.
+const OPENINGS: &[(&str, &str, &[&str])] = &[
+    ("B00", "King's Pawn Game", &["e2 e4"]),
+    ("B01", "Scandinavian Defence", &["e2 e4", "d7 d5"]),
+    ("B07", "Pirc Defence", &["e2 e4", "d7 d6", "d2 d4", "g8 f6"]),
+    ("B10", "Caro-Kann Defence", &["e2 e4", "c7 c6"]),
+    ("B20", "Sicilian Defence", &["e2 e4", "c7 c5"]),
+    ("B90", "Sicilian Defence, Najdorf Variation", &["e2 e4", "c7 c5", "g1 f3", "d7 d6", "d2 d4", "c5 d4", "f3 d4", "g8 f6", "b1 c3", "a7 a6"]),
+    ("C00", "French Defence", &["e2 e4", "e7 e6"]),
+    ("C20", "King's Pawn Game", &["e2 e4", "e7 e5"]),
+    ("C30", "King's Gambit", &["e2 e4", "e7 e5", "f2 f4"]),
+    ("C40", "King's Knight Opening", &["e2 e4", "e7 e5", "g1 f3"]),
+    ("C42", "Petrov's Defence", &["e2 e4", "e7 e5", "g1 f3", "g8 f6"]),
+    ("C44", "King's Pawn Game", &["e2 e4", "e7 e5", "g1 f3", "b8 c6"]),
+    ("C45", "Scotch Game", &["e2 e4", "e7 e5", "g1 f3", "b8 c6", "d2 d4"]),
+    ("C50", "Italian Game", &["e2 e4", "e7 e5", "g1 f3", "b8 c6", "f1 c4"]),
+    ("C60", "Ruy Lopez", &["e2 e4", "e7 e5", "g1 f3", "b8 c6", "f1 b5"]),
+    ("A40", "Queen's Pawn Game", &["d2 d4"]),
+    ("D00", "Queen's Pawn Game", &["d2 d4", "d7 d5"]),
+    ("D06", "Queen's Gambit", &["d2 d4", "d7 d5", "c2 c4"]),
+    ("D10", "Slav Defence", &["d2 d4", "d7 d5", "c2 c4", "c7 c6"]),
+    ("D20", "Queen's Gambit Accepted", &["d2 d4", "d7 d5", "c2 c4", "d5 c4"]),
+    ("D30", "Queen's Gambit Declined", &["d2 d4", "d7 d5", "c2 c4", "e7 e6"]),
+    ("A45", "Indian Defence", &["d2 d4", "g8 f6"]),
+    ("E20", "Nimzo-Indian Defence", &["d2 d4", "g8 f6", "c2 c4", "e7 e6", "b1 c3", "f8 b4"]),
+    ("E60", "King's Indian Defence", &["d2 d4", "g8 f6", "c2 c4", "g7 g6"]),
+    ("A10", "English Opening", &["c2 c4"]),
+    ("A04", "Réti Opening", &["g1 f3"]),
+];
+
+/// Internal helper function returning the openings by the position they lead to, keyed by `key`,
+/// so that an opening is recognised however its moves were ordered.
+fn table() -> &'static HashMap<String, (&'static str, &'static str)> {
+    static TABLE: OnceLock<HashMap<String, (&'static str, &'static str)>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = HashMap::new();
+        for (code, name, moves) in OPENINGS.iter() {
+            let mut game = Game::new();
+            for mov in moves.iter() {
+                game.take_turn(mov.to_string()).expect("opening table move is illegal");
+            }
+            table.insert(key(&game), (*code, *name));
+        }
+        table
+    })
+}
+
+/// Internal helper function returning the piece placement, side to move and castling rights of a game as given by `Game::get_fen_short`.
+/// The en-passant square is left out, since it is set after every double pawn push and would keep transpositions apart.
+fn key(game: &Game) -> String {
+    game.get_fen_short().rsplit_once(' ').map_or_else(String::new, |(key, _)| key.to_string())
+}
+
+impl Game {
+    /// Returns the ECO code and name of the opening played, such as ("B20", "Sicilian Defence"), or None if it isn't in the table.
+    /// The latest position of the game found in the table decides the opening, so moves played after leaving it don't matter.
+    pub fn eco_code(&self) -> Option<(String, String)> {
+        let table = table();
+        let mut previous = self.clone();
+        loop {
+            if let Some((code, name)) = table.get(&key(&previous)) {
+                return Some((code.to_string(), name.to_string()));
+            }
+            previous.undo_move()?;
+        }
+    }
+}
@@ -1,12 +1,138 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+mod eco;
 mod tests;
 
 /// An enumerable representing whether the game has ended or not.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GameState {
     InProgress,
-    Check,
+    /// The king of the given colour is in check.
+    Check(Colour),
     Checkmate,
+    /// The player to move has no legal moves, but isn't in check.
+    Stalemate,
+    /// The game is drawn by the given rule.
+    Draw(DrawReason),
+    /// The given colour won, as the other ran out of time.
+    Timeout(Colour),
+    /// The given colour won, as the other resigned.
+    Resigned(Colour),
+}
+
+/// An enumerable naming the way a position ends the game, as returned by `Game::terminal_kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalKind {
+    /// The side to move is in check and has no legal moves.
+    Checkmate,
+    /// The side to move isn't in check, but has no legal moves.
+    Stalemate,
+    /// The game is drawn for the given reason.
+    Draw(DrawReason),
+}
+
+/// An enumerable naming the rules, other than stalemate, by which a game can be drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    /// A hundred half-moves have been played without a pawn move or a capture. Drawn only once claimed.
+    FiftyMove,
+    /// The same position has occurred three times, with the same side to move. Drawn only once claimed.
+    Threefold,
+    /// A hundred and fifty half-moves have been played without a pawn move or a capture.
+    SeventyFiveMove,
+    /// The same position has occurred five times, with the same side to move.
+    Fivefold,
+    /// Neither side has the pieces left to checkmate.
+    InsufficientMaterial,
+}
+
+/// An enumerable representing the reasons a FEN-notated string can be rejected when parsing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenError {
+    /// The string doesn't consist of exactly six space-separated fields.
+    FieldCount,
+    /// The piece placement contains a character that is neither a piece nor a count of empty squares.
+    Piece(char),
+    /// The piece placement doesn't describe exactly 8 ranks of 8 squares.
+    Dimensions,
+    /// The side to move is neither "w" nor "b".
+    ActiveColour,
+    /// The castling rights contain a character other than K, Q, k, q or -.
+    Castling,
+    /// The en-passant square is neither "-" nor a square in chess notation.
+    EnPassant,
+    /// The halfmove clock isn't a number.
+    Clock,
+    /// The full-move number isn't a number.
+    MoveNumber,
+}
+
+/// An enumerable representing the reasons a move can be rejected by `Game::try_take_turn` or `Game::take_turn_san`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    /// There is no piece on the square moved from.
+    NoPiece,
+    /// The piece on the square moved from belongs to the player whose turn it isn't.
+    WrongColour,
+    /// The piece can't legally move to the square moved to.
+    IllegalMove,
+    /// A move in SAN couldn't be read.
+    Notation,
+    /// A move in SAN matches more than one legal move, and doesn't say which piece moves.
+    Ambiguous,
+    /// The FEN-notated string given to `Game::from_fen_and_moves` couldn't be read.
+    Fen(FenError),
+    /// The move isn't two squares in chess notation separated by a single space, such as "e2 e4".
+    MalformedInput,
+    /// The game has already ended, by checkmate, stalemate, a draw, a timeout or a resignation.
+    GameOver,
+}
+
+/// An enumerable representing the reasons a game in PGN can be rejected by `Game::from_pgn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgnError {
+    /// A tag pair isn't in the form [Name "Value"].
+    Tag,
+    /// The FEN tag holds a position that couldn't be read.
+    Fen(FenError),
+    /// The move with the given index, counted in half-moves from the start of the movetext, isn't a legal move in SAN.
+    Move(usize),
+}
+
+/// An enumerable representing the reasons a position can be rejected by `Game::validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionError {
+    /// The side that is not to move is in check, meaning their king could be captured.
+    IllegalCheck,
+    /// A square holds a character that isn't a FEN piece letter.
+    Piece(char),
+    /// A diagram given to `Game::from_ascii` doesn't have 8 ranks of at most 8 squares.
+    Dimensions,
+}
+
+/// An enumerable naming the rules a game is played by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Standard,
+    /// Captured pieces go to the hand of the side that captured them, and can be dropped back onto the board
+    /// as a move of their own. Promoted pieces go back to the hand as pawns.
+    Crazyhouse,
+}
+
+/// An enumerable naming the stage of the game, as judged by `Game::game_phase`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+/// A struct holding the pawn structure features of one side, as counted by `Game::pawn_structure`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PawnStructure {
+    pub doubled: usize,
+    pub isolated: usize,
+    pub passed: usize,
 }
 
 /// A struct implementing the full state of the chess board.
@@ -16,26 +142,286 @@ pub struct Game {
     pub current_turn: Colour,
     pub castlings: (bool, bool, bool, bool),
     pub en_passant_square: (usize, usize),
+    /// Half-moves since the last pawn move or capture, which both reset it to 0. Used for the fifty-move rule.
     pub halfmove_clock: usize,
+    /// The full-move number, starting at 1 and increased after every move by Black.
     pub turn: usize,
-    pub selected_promotion: Piece,
+    pub white_promotion: PieceKind,
+    pub black_promotion: PieceKind,
+    pub game_state: GameState,
+    pub history: Vec<MoveRecord>,
+    /// The key of the position before each move in the history, for counting repetitions without replaying the game.
+    positions: Vec<PositionKey>,
+    /// The FEN of the position the game was set up from, as returned by `started_from_fen`, or None for the standard starting position.
+    start_fen: Option<String>,
+    /// The moves stepped back over by `goto_ply`, with the next one to replay last. Cleared whenever a new move is played.
+    future: Vec<MoveRecord>,
+    pub time_control: Option<TimeControl>,
+    pub variant: Variant,
+    /// The pieces in the hands of White and Black, in the order they were captured. Only used in Crazyhouse.
+    hands: (Vec<PieceKind>, Vec<PieceKind>),
+    /// The squares of the pieces that got there by promoting, so that they go to the hand as pawns when captured. Only used in Crazyhouse.
+    promoted: BTreeSet<(usize, usize)>,
+    legal_moves_cache: MoveCache,
+    on_move: MoveCallback,
+    promotion_policy: PromotionRule,
+}
+
+/// A cache of the legal moves in a position, kept together with the position they were generated for,
+/// so that they are generated again once the position changes. Caches are ignored when comparing games.
+#[derive(Debug, Default)]
+struct MoveCache(Mutex<Option<CachedMoves>>);
+
+#[derive(Debug, Clone)]
+struct CachedMoves {
+    board: Vec<Vec<Piece>>,
+    current_turn: Colour,
+    castlings: (bool, bool, bool, bool),
+    en_passant_square: (usize, usize),
+    moves: Vec<Move>,
+}
+
+impl Clone for MoveCache {
+    fn clone(&self) -> Self {
+        MoveCache(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+impl PartialEq for MoveCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for MoveCache {}
+
+/// The function called after every move played with `Game::try_take_turn`, as set by `Game::set_on_move`.
+/// Callbacks belong to a single game: copies of the game start without one, and they are ignored when comparing games.
+/// The callback must be `Send` and sits behind a `Mutex`, which is only ever accessed mutably, so that `Game` stays `Send` and `Sync`.
+#[derive(Default)]
+struct MoveCallback(Mutex<Option<Box<OnMove>>>);
+
+type OnMove = dyn FnMut(&MoveOutcome) + Send;
+
+impl Clone for MoveCallback {
+    fn clone(&self) -> Self {
+        MoveCallback::default()
+    }
+}
+
+impl PartialEq for MoveCallback {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for MoveCallback {}
+
+impl std::fmt::Debug for MoveCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let is_set = self.0.lock().unwrap().is_some();
+        f.debug_tuple("MoveCallback").field(&if is_set { "set" } else { "unset" }).finish()
+    }
+}
+
+/// A trait choosing the piece a pawn promotes to, for variants with their own promotion rules, as set by `Game::set_promotion_policy`.
+/// Kinds that can't be promoted to, kings and pawns, are turned into a queen.
+pub trait PromotionPolicy: Send + Sync {
+    /// Returns the kind of piece the pawn moving between the two squares promotes to.
+    ///
+    /// # Arguments
+    ///
+    /// * `colour_is_white`: Whether the pawn is White (true) or Black (false).
+    /// * `from`: The square the pawn moves from, in chess notation.
+    /// * `to`: The square the pawn promotes on, in chess notation.
+    /// * `requested`: The kind given with the move, or the kind selected with `set_default_promotion` if none was given.
+    fn choose(&self, colour_is_white: bool, from: &str, to: &str, requested: PieceKind) -> PieceKind;
+}
+
+/// The promotion policy games start with, promoting to the kind requested, which is a queen unless another kind was asked for.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StandardPromotion;
+
+impl PromotionPolicy for StandardPromotion {
+    fn choose(&self, _colour_is_white: bool, _from: &str, _to: &str, requested: PieceKind) -> PieceKind {
+        requested
+    }
+}
+
+/// The promotion policy of a game. Copies of the game share it, and it is ignored when comparing games.
+#[derive(Clone)]
+struct PromotionRule(Arc<dyn PromotionPolicy>);
+
+impl Default for PromotionRule {
+    fn default() -> Self {
+        PromotionRule(Arc::new(StandardPromotion))
+    }
+}
+
+impl PartialEq for PromotionRule {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for PromotionRule {}
+
+impl std::fmt::Debug for PromotionRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("PromotionRule")
+    }
+}
+
+/// A saved copy of the full state of a game, including its history, as made by `Game::savepoint` and brought back by `Game::restore`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameSnapshot {
+    game: Game,
+}
+
+/// A struct identifying a position, as returned by `Game::position_key`: the board, the side to move, the castling rights,
+/// the en-passant square and, in Crazyhouse, the pieces in hand and which pieces were promoted. Two keys are equal exactly when the positions are,
+/// however and in however many moves they were reached, so keys can be used in maps and sets where a `Game` can't.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PositionKey {
+    board: Vec<Vec<Piece>>,
+    current_turn: Colour,
+    castlings: (bool, bool, bool, bool),
+    en_passant_square: (usize, usize),
+    hands: (Vec<PieceKind>, Vec<PieceKind>),
+    promoted: BTreeSet<(usize, usize)>,
+}
+
+/// A struct describing a move that has just been played, as passed to the callback set with `Game::set_on_move`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveOutcome {
+    /// The square moved from, in chess notation, or the square dropped on for a drop in Crazyhouse.
+    pub from: String,
+    /// The square moved to, in chess notation.
+    pub to: String,
+    pub promotion: Option<PieceKind>,
+    /// The game-state after the move.
+    pub game_state: GameState,
+}
+
+/// A struct holding the remaining time of both players in a timed game, and the increment added after every move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub white_ms: u64,
+    pub black_ms: u64,
+    pub increment_ms: u64,
+}
+
+/// A struct describing a single move, as generated by `Game::all_legal_moves`.
+/// A pawn reaching the last rank gives one move per piece it can promote to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub promotion: Option<PieceKind>,
+    /// The position of the pawn captured en passant, which isn't the position moved to. None for any other move.
+    pub ep_capture: Option<(usize, usize)>,
+    pub kind: MoveKind,
+}
+
+/// An enumerable classifying a move by what it does on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+    Quiet,
+    Capture,
+    /// A pawn moving two squares forward from its starting rank.
+    DoublePawnPush,
+    EnPassant,
+    /// Castling kingside (true) or queenside (false).
+    Castle(bool),
+    Promotion(PieceKind),
+    PromotionCapture(PieceKind),
+}
+
+/// A struct recording a single move that has been played, holding everything needed to take it back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveRecord {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub moved: Piece,
+    pub captured: Piece,
+    pub captured_square: (usize, usize),
+    pub promotion: Option<PieceKind>,
+    pub castlings: (bool, bool, bool, bool),
+    pub en_passant_square: (usize, usize),
+    pub halfmove_clock: usize,
     pub game_state: GameState,
+    /// Whether the move put the opponent in check, including checkmate.
+    pub gives_check: bool,
+    /// The squares the rook moved from and to, if the move was castling.
+    pub castling_rook: Option<((usize, usize), (usize, usize))>,
+    /// Whether the move dropped the moved piece from the hand onto `to`, in Crazyhouse. `from` is the same as `to` for drops.
+    pub dropped: bool,
+    /// Whether the captured piece had been promoted, and so went to the hand as a pawn, in Crazyhouse.
+    pub captured_promoted: bool,
+}
+
+impl MoveRecord {
+    /// Returns the move in SAN if it was castling, "O-O" on the kingside or "O-O-O" on the queenside, and None otherwise.
+    pub fn castling_san(&self) -> Option<&'static str> {
+        let (rook_from, _) = self.castling_rook?;
+        Some(if rook_from.1 == 7 { "O-O" } else { "O-O-O" })
+    }
+}
+
+/// The FEN of the standard starting position.
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// The random numbers that are combined into `Game::zobrist_hash`: one for each of the 12 pieces on each of the 64 squares,
+/// followed by one for Black to move, one for each castling right and one for each file of the en-passant square.
+const ZOBRIST_KEYS: [u64; 781] = zobrist_keys();
+
+/// Internal helper function generating `ZOBRIST_KEYS` with SplitMix64 from a fixed seed, so that hashes stay the same between runs.
+const fn zobrist_keys() -> [u64; 781] {
+    let mut keys = [0; 781];
+    let mut state: u64 = 0x4d75_726e_696f_6e21;
+    let mut idx = 0;
+    while idx < keys.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut key = state;
+        key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[idx] = key ^ (key >> 31);
+        idx += 1;
+    }
+    keys
+}
+
+/// The weights used by `Game::evaluate`, in centipawns: per pawn of material, per legal move, per castling right kept,
+/// and per pawn on one of the four central squares.
+const MATERIAL_WEIGHT: i32 = 100;
+const MOBILITY_WEIGHT: i32 = 5;
+const CASTLING_RIGHT_BONUS: i32 = 10;
+const CENTRAL_PAWN_BONUS: i32 = 20;
+
+/// The weights used by `Game::king_shield`, in centipawns: per pawn directly in front of the king or on a neighbouring file,
+/// per such pawn one square further up, per file in front of the king without a pawn of its own, and per such file without any pawns.
+const SHIELD_PAWN_BONUS: i32 = 10;
+const ADVANCED_SHIELD_PAWN_BONUS: i32 = 5;
+const MISSING_SHIELD_PAWN_PENALTY: i32 = 15;
+const OPEN_FILE_PENALTY: i32 = 10;
+
+/// The score given to checkmate by `search_best_move_timed`, above any evaluation. Mates found sooner score higher.
+const MATE_SCORE: i32 = 1_000_000;
+
+/// How much worse than passing, in centipawns, every move has to be for `Game::is_in_zugzwang_lite`.
+const ZUGZWANG_THRESHOLD: i32 = 50;
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
 }
 
 impl Game {
     /// Creates a new game board, with standard starting positions.
     pub fn new() -> Game {
-        let mut game = Game {
-            board: vec!(vec!(Piece::Empty)),
-            current_turn: Colour::White,
-            castlings: (true, true, true, true),
-            en_passant_square: (0, 0),
-            halfmove_clock: 0,
-            turn: 0,
-            selected_promotion: Piece::Queen(Colour::White),
-            game_state: GameState::InProgress,
-        };
-        game.set_state_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let mut game = Game::new_empty();
+        game.set_state_from_fen(START_FEN);
         game
     }
 
@@ -44,6 +430,14 @@ impl Game {
         self.game_state
     }
 
+    /// Returns the kind and colour of the piece at the given coordinates, or None for an empty square.
+    /// Uses the same coordinates as the board, where rank 0 is the 8th rank and file 0 is the a-file.
+    /// Panics if either coordinate is 8 or larger.
+    pub fn at(&self, rank: usize, file: usize) -> Option<(PieceKind, Colour)> {
+        let piece = self.board[rank][file];
+        Some((piece.kind()?, *piece.get_colour()?))
+    }
+
     /// Creates a new game board, with no pieces on it.
     fn new_empty() -> Game {
         Game {
@@ -53,209 +447,796 @@ impl Game {
             en_passant_square: (8, 8),
             halfmove_clock: 0,
             turn: 1,
-            selected_promotion: Piece::Queen(Colour::White),
+            white_promotion: PieceKind::Queen,
+            black_promotion: PieceKind::Queen,
             game_state: GameState::InProgress,
+            history: Vec::new(),
+            positions: Vec::new(),
+            start_fen: None,
+            future: Vec::new(),
+            time_control: None,
+            variant: Variant::Standard,
+            hands: (Vec::new(), Vec::new()),
+            promoted: BTreeSet::new(),
+            legal_moves_cache: MoveCache::default(),
+            on_move: MoveCallback::default(),
+            promotion_policy: PromotionRule::default(),
         }
     }
 
-    /// Sets the game state using a FEN-notated string.
-    /// Note that currently it does not check for nor handle any case wherein the string given is not in FEN-notation.
-    /// 
+    /// Creates a copy of the current position, without the move history.
+    /// Meant for search trees and other places where many copies are made; note that `undo_move` won't work on the clone,
+    /// as it has no record of how the position was reached.
+    pub fn clone_position(&self) -> Game {
+        let mut game = Game {
+            board: self.board.clone(),
+            current_turn: self.current_turn,
+            castlings: self.castlings,
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            turn: self.turn,
+            white_promotion: self.white_promotion,
+            black_promotion: self.black_promotion,
+            game_state: self.game_state,
+            history: Vec::new(),
+            positions: Vec::new(),
+            start_fen: None,
+            future: Vec::new(),
+            time_control: self.time_control,
+            variant: self.variant,
+            hands: self.hands.clone(),
+            promoted: self.promoted.clone(),
+            legal_moves_cache: MoveCache::default(),
+            on_move: MoveCallback::default(),
+            promotion_policy: self.promotion_policy.clone(),
+        };
+        game.start_fen = game.custom_fen();
+        game
+    }
+
+    /// Returns a key identifying the current position, which is equal for every game reaching the same position,
+    /// whatever the move order, the number of moves played or the move clocks. The pieces in hand are compared regardless of the order they were captured in.
+    pub fn position_key(&self) -> PositionKey {
+        let mut hands = self.hands.clone();
+        hands.0.sort_by_key(|kind| *kind as u8);
+        hands.1.sort_by_key(|kind| *kind as u8);
+        PositionKey {
+            board: self.board.clone(),
+            current_turn: self.current_turn,
+            castlings: self.castlings,
+            en_passant_square: self.en_passant_square,
+            hands,
+            promoted: self.promoted.clone(),
+        }
+    }
+
+    /// Saves the full state of the game, with its history and the moves stepped back over by `goto_ply`,
+    /// so that it can be brought back with `restore` however the game goes on in the meantime.
+    pub fn savepoint(&self) -> GameSnapshot {
+        GameSnapshot { game: self.clone() }
+    }
+
+    /// Brings the game back to the state saved in a snapshot. The function set with `set_on_move` is kept, as snapshots don't hold one.
+    pub fn restore(&mut self, snap: GameSnapshot) {
+        let on_move = std::mem::take(&mut self.on_move);
+        *self = snap.game;
+        self.on_move = on_move;
+    }
+
+    /// Creates a new game from a FEN-notated string, returning what was wrong with the string if it couldn't be read.
+    ///
     /// # Arguments
-    /// 
+    ///
     /// * `fen` - string in FEN-notation containing the desired state of the chess game.
-    pub fn set_state_from_fen(&mut self, fen: &str) {
-        let fen_split = fen.split(" ").map(|_s| _s.to_string()).collect::<Vec<String>>();
-        assert_eq!(fen_split.len(), 6, "Given invalid string when attempting to set state from FEN notaion.");
-        self.board = {
-            fen_split[0].split("/")
-                        .map(|_rank| { 
-                            let mut c_rank = Vec::new();
-                            for _char in _rank.chars() { match _char {
-                                'K' => c_rank.push(Piece::King(Colour::White)),
-                                'k' => c_rank.push(Piece::King(Colour::Black)),
-                                'Q' => c_rank.push(Piece::Queen(Colour::White)),
-                                'q' => c_rank.push(Piece::Queen(Colour::Black)),
-                                'R' => c_rank.push(Piece::Rook(Colour::White)),
-                                'r' => c_rank.push(Piece::Rook(Colour::Black)),
-                                'B' => c_rank.push(Piece::Bishop(Colour::White)),
-                                'b' => c_rank.push(Piece::Bishop(Colour::Black)),
-                                'N' => c_rank.push(Piece::Knight(Colour::White)),
-                                'n' => c_rank.push(Piece::Knight(Colour::Black)),
-                                'P' => c_rank.push(Piece::Pawn(Colour::White)),
-                                'p' => c_rank.push(Piece::Pawn(Colour::Black)),
-                                _ => for _ in 0.._char.to_digit(10).unwrap() as usize { c_rank.push(Piece::Empty); },
-                            }};
-                            return c_rank
-                        }).collect::<Vec<Vec<Piece>>>()
-        };
-        self.current_turn = match fen_split[1].chars().collect::<Vec<char>>()[0] {
-            'w' => Colour::White,
-            'b' => Colour::Black,
-            _ => panic!(),
-        };
-        self.castlings = (fen_split[2].contains('K'), fen_split[2].contains('Q'), fen_split[2].contains('k'), fen_split[2].contains('q'));
-        self.en_passant_square = {
-            let fen_chars = fen_split[3].chars().collect::<Vec<char>>();
-            if fen_chars[0] == '-' {
-                let square = (8, 8);
-                square
-            } else {
-            let x: usize = match fen_chars[0] {
-                'a' => 0,
-                'b' => 1,
-                'c' => 2,
-                'd' => 3,
-                'e' => 4,
-                'f' => 5,
-                'g' => 6,
-                'h' => 7,
-                _ => panic!(),
-            };
-            let y: usize = fen_chars[1].to_digit(10).unwrap() as usize;
-            let square = (8 - y, x);
-            square
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        let mut game = Game::new_empty();
+        game.try_set_state_from_fen(fen)?;
+        Ok(game)
+    }
+
+    /// Creates a new game from a FEN-notated string, reading as much of it as it can instead of giving up at the first problem,
+    /// and returns it together with every problem found. Anything that can't be read is replaced: unknown piece letters by empty squares,
+    /// missing squares and ranks by empty ones, an unknown side to move by White, unknown castling letters by no right,
+    /// a bad en-passant square by none, and missing or bad clocks by 0 and 1. The list of problems is empty for a valid FEN.
+    ///
+    /// # Arguments
+    ///
+    /// * `fen` - string in FEN-notation, possibly damaged, containing the desired state of the chess game.
+    pub fn from_fen_lossy(fen: &str) -> (Game, Vec<FenError>) {
+        let mut errors = Vec::new();
+        let fen_split = fen.trim().split(' ').collect::<Vec<&str>>();
+        if fen_split.len() != 6 {
+            errors.push(FenError::FieldCount);
+        }
+        let field = |idx: usize| fen_split.get(idx).copied().unwrap_or("");
+
+        let mut game = Game::new_empty();
+        game.board = vec![vec![Piece::Empty; 8]; 8];
+        let mut dimensions = fen_split[0].split('/').count() == 8;
+        for (x, _rank) in fen_split[0].split('/').take(8).enumerate() {
+            let mut y = 0;
+            for _char in _rank.chars() {
+                let (piece, count) = match _char {
+                    '1'..='8' => (Piece::Empty, _char.to_digit(10).unwrap() as usize),
+                    _ => (piece_from_char(_char).unwrap_or_else(|| {
+                        errors.push(FenError::Piece(_char));
+                        Piece::Empty
+                    }), 1),
+                };
+                for _ in 0..count {
+                    if y < 8 { game.board[x][y] = piece; }
+                    y += 1;
+                }
             }
+            dimensions &= y == 8;
+        }
+        if !dimensions {
+            errors.push(FenError::Dimensions);
+        }
+
+        game.current_turn = match field(1) {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            _ => {
+                errors.push(FenError::ActiveColour);
+                Colour::White
+            },
         };
-        self.halfmove_clock = fen_split[4].parse::<usize>().unwrap();
-        self.turn = fen_split[5].parse::<usize>().unwrap();
+        if !field(2).chars().all(|_char| "KQkq-".contains(_char)) {
+            errors.push(FenError::Castling);
+        }
+        game.castlings = (field(2).contains('K'), field(2).contains('Q'), field(2).contains('k'), field(2).contains('q'));
+        let en_passant = field(3).chars().collect::<Vec<char>>();
+        if en_passant.len() == 2 && ('a'..='h').contains(&en_passant[0]) && ('1'..='8').contains(&en_passant[1]) {
+            game.en_passant_square = convert_square(field(3));
+        } else if field(3) != "-" {
+            errors.push(FenError::EnPassant);
+        }
+        game.halfmove_clock = parse_fen_number(field(4)).unwrap_or_else(|| {
+            errors.push(FenError::Clock);
+            0
+        });
+        game.turn = parse_fen_number(field(5)).unwrap_or_else(|| {
+            errors.push(FenError::MoveNumber);
+            1
+        });
+        game.start_fen = game.custom_fen();
+        game.game_state = game.position_state();
+        (game, errors)
     }
 
-    /// Parses the current board to get the game-state. Returns the new game-state.
-    fn get_game_state_no_recursion(&self) -> GameState {
-        let mut threatened_squares: HashSet<(usize, usize)> = HashSet::new();
-        for x in 0..8 {
-            for y in 0..8 {
-                if self.board[x][y] != Piece::Empty && &self.current_turn != self.board[x][y].get_colour().unwrap() {
-                    threatened_squares.extend(&self.board[x][y]
-                                        .get_threatened_squares((x, y), &self.board)
-                                        .into_iter()
-                                        .collect::<HashSet<(usize, usize)>>());
-                }
+    /// Creates a new game from a line of Extended Position Description, returning it together with the operations that follow the position
+    /// as pairs of opcode and operand, such as ("bm", "e4") or ("id", "\"test\""). Operands are kept as written, quotes included,
+    /// and semicolons inside quotes don't end an operation. The move clocks are taken from the `hmvc` and `fmvn` operations if present,
+    /// and are 0 and 1 otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `epd` - a line of EPD: the first four fields of a FEN-notated string followed by any number of operations.
+    pub fn from_epd(epd: &str) -> Result<(Game, Vec<(String, String)>), FenError> {
+        let mut rest = epd.trim();
+        let mut fields = Vec::new();
+        for _ in 0..4 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(FenError::FieldCount);
             }
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
         }
-        for x in 0..8 {
-            for y in 0..8 {
-                if self.board[x][y] == Piece::King(self.current_turn) && threatened_squares.contains(&(x, y)) {
-                    return GameState::Check;
-                }
+
+        let mut ops = Vec::new();
+        let mut op = String::new();
+        let mut quoted = false;
+        for _char in rest.chars() {
+            match _char {
+                ';' if !quoted => {
+                    let (opcode, operand) = op.trim().split_once(char::is_whitespace).unwrap_or((op.trim(), ""));
+                    if !opcode.is_empty() {
+                        ops.push((opcode.to_string(), operand.trim().to_string()));
+                    }
+                    op.clear();
+                },
+                _ => {
+                    if _char == '"' { quoted = !quoted; }
+                    op.push(_char);
+                },
             }
         }
-        return GameState::InProgress;
+
+        let operand = |opcode: &str| ops.iter().find(|(code, _)| code == opcode).map(|(_, operand)| operand.as_str());
+        let fen = format!("{} {} {}", fields.join(" "), operand("hmvc").unwrap_or("0"), operand("fmvn").unwrap_or("1"));
+        let game = Game::from_fen(&fen)?;
+        Ok((game, ops))
     }
 
-    /// Recursively parses the board to get the game-state. Returns the new game-state.
-    fn get_game_state(&self, eot: bool) -> GameState {
-        let mut state = self.get_game_state_no_recursion();
-        let mut moves = Vec::new();
-        if state == GameState::Check && eot {
-            for x in 0..8 {
-                for y in 0..8 {
-                    if self.board[x][y] != Piece::Empty && self.board[x][y].get_colour().unwrap() == &self.current_turn {
-                        moves.append(&mut self.board[x][y].get_valid_moves((x, y), &self.board, self.en_passant_square, self.castlings, self.current_turn));
-                    }
+    /// Creates a new game by replaying a single game in PGN, returning what was wrong with it if it couldn't be read.
+    /// The game starts from the position in the FEN tag if there is one, and from the standard position otherwise.
+    /// Only the mainline is played: other tags, comments, variations, NAGs and the result token ending the movetext are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `pgn` - string holding the tag pairs and the movetext of the game, with moves in SAN.
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+        let mut game = Game::new();
+        let mut movetext = String::new();
+        for line in pgn.lines() {
+            let line = line.trim();
+            if line.starts_with('%') {
+                continue;
+            } else if line.starts_with('[') {
+                let (name, value) = line.strip_prefix('[')
+                    .and_then(|tag| tag.strip_suffix(']'))
+                    .and_then(|tag| tag.split_once(' '))
+                    .ok_or(PgnError::Tag)?;
+                let value = value.trim().strip_prefix('"').and_then(|value| value.strip_suffix('"')).ok_or(PgnError::Tag)?;
+                if name == "FEN" {
+                    game = Game::from_fen(value).map_err(PgnError::Fen)?;
                 }
+            } else {
+                movetext.push_str(line);
+                movetext.push('\n');
             }
-            if moves.len() == 0 {
-                state = GameState::Checkmate;
+        }
+
+        let mut idx = 0;
+        for token in pgn_mainline(&movetext).split_whitespace() {
+            if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+                break;
+            }
+            if token.starts_with('$') {
+                continue;
             }
+            let san = match token.rfind('.') {
+                Some(dot) => &token[dot + 1..],
+                None => token,
+            };
+            if san.is_empty() {
+                continue;
+            }
+            game.take_turn_san(san).map_err(|_| PgnError::Move(idx))?;
+            idx += 1;
         }
-        state
+        Ok(game)
     }
 
-    /// Takes a char of either r, q, n, or b, setting the promotion to be Rook, Queen, Knight or Bishop.
-    pub fn select_promotion(&mut self, piece: char) {
-        match piece.to_lowercase().next().unwrap() {
-            'r' => self.selected_promotion = Piece::Rook(self.current_turn),
-            'b' => self.selected_promotion = Piece::Bishop(self.current_turn),
-            'n' => self.selected_promotion = Piece::Knight(self.current_turn),
-            'q' => self.selected_promotion = Piece::Queen(self.current_turn),
-            _ => panic!()
+    /// Creates a new game from a FEN-notated string and the moves played from that position,
+    /// the most compact way to write down how a game reached a position. Each move is in UCI notation, such as "e2e4" or "e7e8q",
+    /// or in the form taken by `take_turn`. Returns the reason the string or the first rejected move couldn't be used.
+    ///
+    /// # Arguments
+    ///
+    /// * `fen` - string in FEN-notation containing the position the moves are played from.
+    /// * `moves` - the moves to play, in order.
+    pub fn from_fen_and_moves(fen: &str, moves: &[&str]) -> Result<Game, MoveError> {
+        let mut game = Game::from_fen(fen).map_err(MoveError::Fen)?;
+        for mov in moves {
+            game.try_take_turn_uci(mov)?;
         }
+        Ok(game)
     }
 
-    /// Returns the state of the game as a string in FEN-notation.
-    pub fn get_fen(&self) -> String {
-        use std::char;
+    /// Creates a new game from a board of FEN piece letters, with None for empty squares.
+    /// The first row of the array is rank 8, and the first column is the a-file.
+    /// Castling rights are all off and there is no en-passant square. The position is checked with `validate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `board`: The pieces on the board, such as Some('K') for a White king.
+    /// * `white_to_move`: Whether it is White's (true) or Black's (false) turn.
+    pub fn from_board_array(board: [[Option<char>; 8]; 8], white_to_move: bool) -> Result<Game, PositionError> {
+        let mut game = Game::new_empty();
+        let mut pieces = Vec::new();
+        for rank in board.iter() {
+            let mut c_rank = Vec::new();
+            for square in rank.iter() {
+                match square {
+                    Some(_char) => c_rank.push(piece_from_char(*_char).ok_or(PositionError::Piece(*_char))?),
+                    None => c_rank.push(Piece::Empty),
+                }
+            }
+            pieces.push(c_rank);
+        }
+        game.board = pieces;
+        game.current_turn = if white_to_move { Colour::White } else { Colour::Black };
+        game.castlings = (false, false, false, false);
+        game.validate()?;
+        game.start_fen = game.custom_fen();
+        game.game_state = game.position_state();
+        Ok(game)
+    }
 
-        let mut fen: String = "".to_string();
-        for x in 0..8 {
-            let mut rank: String = "".to_string();
-            let mut empties = 0;
-            for y in 0..8 {
-                match self.board[x][y] {
-                    Piece::Empty => empties = empties + 1,
-                    Piece::King(colour) => {
-                        if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); empties = 0; }
-                        if colour == Colour::White {
-                            rank.push('K');
-                        } else {
-                            rank.push('k');
-                        }
-                    },
-                    Piece::Queen(colour) => {
-                        if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); empties = 0; }
-                        if colour == Colour::White {
-                            rank.push('Q');
-                        } else {
-                            rank.push('q');
-                        }
-                    },
-                    Piece::Bishop(colour) => {
-                        if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); empties = 0; }
-                        if colour == Colour::White {
-                            rank.push('B');
-                        } else {
-                            rank.push('b');
-                        }
-                    },
-                    Piece::Rook(colour) => {
-                        if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); empties = 0; }
-                        if colour == Colour::White {
-                            rank.push('R');
-                        } else {
-                            rank.push('r');
-                        }
-                    },
-                    Piece::Knight(colour) => {
-                        if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); empties = 0; }
-                        if colour == Colour::White {
-                            rank.push('N');
-                        } else {
-                            rank.push('n');
-                        }
-                    },
-                    Piece::Pawn(colour) => {
-                        if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); empties = 0; }
-                        if colour == Colour::White {
-                            rank.push('P');
-                        } else {
-                            rank.push('p');
-                        }
-                    },
+    /// Creates a new game from a diagram of the board, with one line per rank starting with rank 8,
+    /// using FEN piece letters for pieces and '.' or a space for empty squares. Blank lines before the first rank and after the last are skipped,
+    /// and a line shorter than 8 squares is filled up with empty squares. The position is set up as by `from_board_array`.
+    ///
+    /// # Arguments
+    ///
+    /// * `art`: The diagram of the board, such as "....k...\n" for the first line of a board with a Black king on e8.
+    /// * `white_to_move`: Whether it is White's (true) or Black's (false) turn.
+    pub fn from_ascii(art: &str, white_to_move: bool) -> Result<Game, PositionError> {
+        let lines = art.lines().collect::<Vec<&str>>();
+        let first = lines.iter().position(|line| !line.trim().is_empty()).unwrap_or(0);
+        let last = lines.iter().rposition(|line| !line.trim().is_empty()).map_or(0, |last| last + 1);
+        let ranks = &lines[first..last.max(first)];
+        if ranks.len() != 8 {
+            return Err(PositionError::Dimensions);
+        }
+        let mut board = [[None; 8]; 8];
+        for (x, rank) in ranks.iter().enumerate() {
+            let squares = rank.trim_end().chars().collect::<Vec<char>>();
+            if squares.len() > 8 {
+                return Err(PositionError::Dimensions);
+            }
+            for (y, square) in squares.into_iter().enumerate() {
+                if square != '.' && square != ' ' {
+                    board[x][y] = Some(square);
                 }
             }
-            if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); }
-            if x != 7 { rank.push('/'); }
-            fen.push_str(&rank);
         }
+        Game::from_board_array(board, white_to_move)
+    }
 
-        if self.current_turn == Colour::White {
-            fen.push_str(" w ");
-        } else {
-            fen.push_str(" b ");
+    /// Sets the game state using a FEN-notated string.
+    /// Panics if the string given is not in FEN-notation; use `try_set_state_from_fen` to handle that case instead.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `fen` - string in FEN-notation containing the desired state of the chess game.
+    pub fn set_state_from_fen(&mut self, fen: &str) {
+        if let Err(error) = self.try_set_state_from_fen(fen) {
+            panic!("Given invalid string when attempting to set state from FEN notation: {:?}", error);
         }
+    }
 
-        if self.castlings.0 {fen.push_str("K")}
-        if self.castlings.1 {fen.push_str("Q")}
-        if self.castlings.2 {fen.push_str("k")}
-        if self.castlings.3 {fen.push_str("q")}
+    /// Sets the game state using a FEN-notated string, returning what was wrong with the string if it couldn't be read.
+    /// The game is left unchanged when an error is returned. Otherwise the history, the pieces in hand and the time control
+    /// are cleared, as the FEN doesn't describe them.
+    ///
+    /// # Arguments
+    ///
+    /// * `fen` - string in FEN-notation containing the desired state of the chess game.
+    pub fn try_set_state_from_fen(&mut self, fen: &str) -> Result<(), FenError> {
+        let fen_split = fen.split(' ').collect::<Vec<&str>>();
+        if fen_split.len() != 6 {
+            return Err(FenError::FieldCount);
+        }
+        if fen_split[0].split('/').count() != 8 {
+            return Err(FenError::Dimensions);
+        }
+        let mut board = Vec::new();
+        for _rank in fen_split[0].split('/') {
+            let mut c_rank = Vec::new();
+            for _char in _rank.chars() { match _char {
+                '1'..='8' => for _ in 0.._char.to_digit(10).unwrap() as usize { c_rank.push(Piece::Empty); },
+                _ => c_rank.push(piece_from_char(_char).ok_or(FenError::Piece(_char))?),
+            }};
+            if c_rank.len() != 8 {
+                return Err(FenError::Dimensions);
+            }
+            board.push(c_rank);
+        }
+        let current_turn = match fen_split[1] {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            _ => return Err(FenError::ActiveColour),
+        };
+        if !fen_split[2].chars().all(|_char| "KQkq-".contains(_char)) {
+            return Err(FenError::Castling);
+        }
+        let castlings = (fen_split[2].contains('K'), fen_split[2].contains('Q'), fen_split[2].contains('k'), fen_split[2].contains('q'));
+        let en_passant_square = if fen_split[3] == "-" {
+            (8, 8)
+        } else {
+            let fen_chars = fen_split[3].chars().collect::<Vec<char>>();
+            if fen_chars.len() != 2 || !('a'..='h').contains(&fen_chars[0]) || !('1'..='8').contains(&fen_chars[1]) {
+                return Err(FenError::EnPassant);
+            }
+            convert_square(fen_split[3])
+        };
+        let halfmove_clock = parse_fen_number(fen_split[4]).ok_or(FenError::Clock)?;
+        let turn = parse_fen_number(fen_split[5]).ok_or(FenError::MoveNumber)?;
 
-        let x = self.en_passant_square.0;
-        let y = self.en_passant_square.1;
+        self.board = board;
+        self.current_turn = current_turn;
+        self.castlings = castlings;
+        self.en_passant_square = en_passant_square;
+        self.halfmove_clock = halfmove_clock;
+        self.turn = turn;
+        self.history.clear();
+        self.positions.clear();
+        self.future.clear();
+        self.hands = (Vec::new(), Vec::new());
+        self.promoted.clear();
+        self.time_control = None;
+        self.start_fen = self.custom_fen();
+        self.game_state = self.position_state();
+        Ok(())
+    }
 
-        fen.push(' ');
-        match y {
+    /// Removes every castling right whose king or rook isn't on its home square, such as the stale rights often found in scraped FENs.
+    pub fn sanitize_castling_rights(&mut self) {
+        let at_home = |x: usize, y: usize, piece: Piece| self.board[x][y] == piece;
+        let white_king = at_home(7, 4, Piece::King(Colour::White));
+        let black_king = at_home(0, 4, Piece::King(Colour::Black));
+        self.castlings = (
+            self.castlings.0 && white_king && at_home(7, 7, Piece::Rook(Colour::White)),
+            self.castlings.1 && white_king && at_home(7, 0, Piece::Rook(Colour::White)),
+            self.castlings.2 && black_king && at_home(0, 7, Piece::Rook(Colour::Black)),
+            self.castlings.3 && black_king && at_home(0, 0, Piece::Rook(Colour::Black)),
+        );
+    }
+
+    /// Checks that the position is one that could occur in a game, returning the reason it couldn't otherwise.
+    /// Currently this rejects positions where the side that just moved has left the opponent able to capture their king.
+    pub fn validate(&self) -> Result<(), PositionError> {
+        let waiting = self.current_turn.opposite();
+        if self.is_king_threatened(waiting) {
+            return Err(PositionError::IllegalCheck);
+        }
+        Ok(())
+    }
+
+    /// Returns whether neither side has more pieces than a game could give them: at most one king and eight pawns,
+    /// and no more queens, rooks, bishops and knights beyond the starting ones than the pawns missing could have promoted to.
+    pub fn has_legal_material(&self) -> bool {
+        [Colour::White, Colour::Black].iter().all(|&colour| {
+            let count = |piece: Piece| self.board.iter().flatten().filter(|&&square| square == piece).count();
+            let pawns = count(Piece::Pawn(colour));
+            let promoted = count(Piece::Queen(colour)).saturating_sub(1)
+                + count(Piece::Rook(colour)).saturating_sub(2)
+                + count(Piece::Bishop(colour)).saturating_sub(2)
+                + count(Piece::Knight(colour)).saturating_sub(2);
+            count(Piece::King(colour)) <= 1 && pawns <= 8 && promoted <= 8 - pawns
+        })
+    }
+
+    /// Returns the squares the piece with the given FEN letter could be placed on, replacing what is there, without making the position
+    /// obviously illegal: never onto a king, no pawns on the first or last rank, and only where the result still passes `validate`
+    /// and `has_legal_material`. Returns an empty list for a character that isn't a FEN piece letter.
+    pub fn legal_placements(&self, piece: char) -> Vec<String> {
+        let piece = match piece_from_char(piece) {
+            Some(piece) => piece,
+            None => return Vec::new(),
+        };
+        let mut squares = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                if self.board[x][y].kind() == Some(PieceKind::King) || (piece.kind() == Some(PieceKind::Pawn) && (x == 0 || x == 7)) {
+                    continue;
+                }
+                let mut placed = self.clone_position();
+                placed.board[x][y] = piece;
+                if placed.validate().is_ok() && placed.has_legal_material() {
+                    squares.push(square_name((x, y)));
+                }
+            }
+        }
+        squares
+    }
+
+    /// Returns how the current position ends the game for the side to move, or None if the game can go on.
+    /// The endings that apply on their own are looked for first, in the order of `automatic_end`,
+    /// followed by the draws a player can claim, as given by `claimable_draw`.
+    pub fn terminal_kind(&self) -> Option<TerminalKind> {
+        self.automatic_end().or_else(|| self.claimable_draw().map(TerminalKind::Draw))
+    }
+
+    /// Internal helper function returning the game-state of the current position, as set after every move and when a position is loaded:
+    /// the way `automatic_end` ends the game if it does, or else whether the player whose turn it is is in check.
+    fn position_state(&self) -> GameState {
+        match self.automatic_end() {
+            Some(TerminalKind::Checkmate) => GameState::Checkmate,
+            Some(TerminalKind::Stalemate) => GameState::Stalemate,
+            Some(TerminalKind::Draw(reason)) => GameState::Draw(reason),
+            None => self.get_game_state_no_recursion(),
+        }
+    }
+
+    /// Internal helper function returning how the current position ends the game without either player claiming anything:
+    /// checkmate and stalemate first, followed by the seventy-five-move rule, fivefold repetition and insufficient material.
+    /// The state set by every move is taken from this, so `game_state` follows the same order.
+    fn automatic_end(&self) -> Option<TerminalKind> {
+        // A lone king always has a square to go to, so with only kings left there is no need to generate moves.
+        let only_kings = self.only_kings();
+        if !only_kings && self.all_legal_moves().is_empty() && !self.has_legal_drop() {
+            if self.is_king_threatened(self.current_turn) {
+                return Some(TerminalKind::Checkmate);
+            }
+            return Some(TerminalKind::Stalemate);
+        }
+        if self.halfmove_clock >= 150 {
+            return Some(TerminalKind::Draw(DrawReason::SeventyFiveMove));
+        }
+        // A position can't have occurred five times unless at least sixteen reversible half-moves have been played.
+        if self.halfmove_clock >= 16 && self.repetition_count() >= 5 {
+            return Some(TerminalKind::Draw(DrawReason::Fivefold));
+        }
+        if (only_kings || self.insufficient_material()) && self.hands.0.is_empty() && self.hands.1.is_empty() {
+            return Some(TerminalKind::Draw(DrawReason::InsufficientMaterial));
+        }
+        None
+    }
+
+    /// Returns the draw the player whose turn it is could claim, if any: the fifty-move rule, or failing that, threefold repetition.
+    /// Unlike the other ways of drawing, these don't end the game by themselves; use `claim_draw` to end it.
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.halfmove_clock >= 100 {
+            return Some(DrawReason::FiftyMove);
+        }
+        // A position can't have occurred three times unless at least eight reversible half-moves have been played.
+        if self.halfmove_clock >= 8 && self.repetition_count() >= 3 {
+            return Some(DrawReason::Threefold);
+        }
+        None
+    }
+
+    /// Ends the game as a draw if the player whose turn it is can claim one, as given by `claimable_draw`.
+    /// Returns the new game-state, or None if there is no draw to claim, leaving the game unchanged.
+    pub fn claim_draw(&mut self) -> Option<GameState> {
+        let reason = self.claimable_draw()?;
+        self.game_state = GameState::Draw(reason);
+        Some(self.game_state)
+    }
+
+    /// Returns whether the current position, compared by board, side to move, castling rights and en-passant square,
+    /// already occurred earlier in the game, such as for warning that a position is being repeated before threefold repetition.
+    pub fn position_seen_before(&self) -> bool {
+        // A position takes at least four half-moves without a capture or pawn move to come back.
+        self.halfmove_clock >= 4 && self.repetition_count() >= 2
+    }
+
+    /// Internal helper function counting how many times the current position has occurred in the game, including now.
+    /// Positions are the same if their `position_key`s are. Only the positions since the last capture or pawn move are looked at,
+    /// as no earlier position can come back.
+    fn repetition_count(&self) -> usize {
+        let current = self.position_key();
+        let since = self.positions.len().saturating_sub(self.halfmove_clock);
+        1 + self.positions[since..].iter().filter(|key| **key == current).count()
+    }
+
+    /// Returns whether the two kings are the only pieces left on the board, the simplest case of insufficient material.
+    pub fn only_kings(&self) -> bool {
+        self.board.iter().flatten().all(|piece| matches!(piece, Piece::King(_) | Piece::Empty))
+    }
+
+    /// Internal helper function returning whether neither side has the pieces left to checkmate:
+    /// only kings, a king and a single minor piece against a lone king, or only bishops on squares of the same colour.
+    fn insufficient_material(&self) -> bool {
+        !self.has_mating_material(None)
+    }
+
+    /// Returns whether the given colour has the pieces needed to checkmate a lone king: anything but a lone king,
+    /// a king and a single knight, or a king and bishops that all stand on squares of the same colour.
+    pub fn can_force_mate_material(&self, colour_is_white: bool) -> bool {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        self.has_mating_material(Some(colour))
+    }
+
+    /// Returns whether the material on the board is one of a few endings that are well known to be drawn with correct play,
+    /// for adjudicating games: a rook each, a rook against a bishop, a knight each, or a bishop each on squares of opposite colours,
+    /// all without pawns. Only the material is looked at, so a position that loses at once to a tactic can still be reported.
+    /// Positions drawn by insufficient material aren't included.
+    pub fn is_trivial_draw_endgame(&self) -> bool {
+        let mut pieces = Vec::new();
+        for (x, rank) in self.board.iter().enumerate() {
+            for (y, piece) in rank.iter().enumerate() {
+                match piece {
+                    Piece::King(_) | Piece::Empty => (),
+                    _ => pieces.push((*piece, (x + y) % 2)),
+                }
+            }
+        }
+        if pieces.len() != 2 || pieces[0].0.get_colour() == pieces[1].0.get_colour() {
+            return false;
+        }
+        match (pieces[0].0.kind(), pieces[1].0.kind()) {
+            (Some(PieceKind::Rook), Some(PieceKind::Rook | PieceKind::Bishop)) | (Some(PieceKind::Bishop), Some(PieceKind::Rook)) => true,
+            (Some(PieceKind::Knight), Some(PieceKind::Knight)) => true,
+            (Some(PieceKind::Bishop), Some(PieceKind::Bishop)) => pieces[0].1 != pieces[1].1,
+            _ => false,
+        }
+    }
+
+    /// Internal helper function returning whether the pieces of the given colour, or of both colours if None,
+    /// are enough to checkmate with. Knights and bishops are counted together across both colours when looking at both.
+    fn has_mating_material(&self, colour: Option<Colour>) -> bool {
+        let mut knights = 0;
+        let mut bishop_square_colours = HashSet::new();
+        for (x, rank) in self.board.iter().enumerate() {
+            for (y, piece) in rank.iter().enumerate() {
+                if colour.is_some() && piece.get_colour() != colour.as_ref() { continue; }
+                match piece {
+                    Piece::King(_) | Piece::Empty => (),
+                    Piece::Bishop(_) => { bishop_square_colours.insert((x + y) % 2); },
+                    Piece::Knight(_) => knights += 1,
+                    _ => return true,
+                }
+            }
+        }
+        match knights {
+            0 => bishop_square_colours.len() > 1,
+            1 => !bishop_square_colours.is_empty(),
+            _ => true,
+        }
+    }
+
+    /// Parses the current board to get the game-state. Returns the new game-state.
+    fn get_game_state_no_recursion(&self) -> GameState {
+        if self.is_king_threatened(self.current_turn) {
+            return GameState::Check(self.current_turn);
+        }
+        GameState::InProgress
+    }
+
+    /// Returns whether the king of the given colour stands on a square threatened by the other colour.
+    fn is_king_threatened(&self, colour: Colour) -> bool {
+        let mut threatened_squares: HashSet<(usize, usize)> = HashSet::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                if self.board[x][y] != Piece::Empty && &colour != self.board[x][y].get_colour().unwrap() {
+                    threatened_squares.extend(&self.board[x][y]
+                                        .get_threatened_squares((x, y), &self.board)
+                                        .into_iter()
+                                        .collect::<HashSet<(usize, usize)>>());
+                }
+            }
+        }
+        for x in 0..8 {
+            for y in 0..8 {
+                if self.board[x][y] == Piece::King(colour) && threatened_squares.contains(&(x, y)) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Recursively parses the board to get the game-state. Returns the new game-state.
+    fn get_game_state(&self, eot: bool) -> GameState {
+        let mut state = self.get_game_state_no_recursion();
+        let mut moves = Vec::new();
+        if matches!(state, GameState::Check(_)) && eot {
+            for x in 0..8 {
+                for y in 0..8 {
+                    if self.board[x][y] != Piece::Empty && self.board[x][y].get_colour().unwrap() == &self.current_turn {
+                        moves.append(&mut self.board[x][y].get_valid_moves((x, y), &self.board, self.en_passant_square, self.castlings, self.current_turn));
+                    }
+                }
+            }
+            if moves.is_empty() {
+                state = GameState::Checkmate;
+            }
+        }
+        state
+    }
+
+    /// Takes a char of either r, q, n, or b, setting the promotion of the player whose turn it is to be Rook, Queen, Knight or Bishop.
+    pub fn select_promotion(&mut self, piece: char) {
+        let kind = match piece.to_lowercase().next().unwrap() {
+            'r' => PieceKind::Rook,
+            'b' => PieceKind::Bishop,
+            'n' => PieceKind::Knight,
+            'q' => PieceKind::Queen,
+            _ => panic!()
+        };
+        self.set_default_promotion(self.current_turn.is_white(), kind);
+    }
+
+    /// Sets the piece that pawns of the given colour promote to, kept until changed again.
+    ///
+    /// # Arguments
+    ///
+    /// * `colour_is_white`: Whether to set the promotion of White (true) or Black (false).
+    /// * `kind`: The kind of piece to promote to. Kings and pawns aren't valid promotions, and promote to a queen instead.
+    pub fn set_default_promotion(&mut self, colour_is_white: bool, kind: PieceKind) {
+        if colour_is_white {
+            self.white_promotion = kind;
+        } else {
+            self.black_promotion = kind;
+        }
+    }
+
+    /// Returns the pieces a pawn can promote to, as the chars accepted by `select_promotion`, if moving from one square to the other
+    /// is a legal promotion for the player whose turn it is. Returns None for any other move.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The square the pawn moves from, in chess notation.
+    /// * `to`: The square the pawn moves to, in chess notation.
+    pub fn promotion_choices(&self, from: &str, to: &str) -> Option<Vec<char>> {
+        if !Game::is_valid_square(from) || !Game::is_valid_square(to) {
+            return None;
+        }
+        let from = convert_square(from);
+        let to = convert_square(to);
+        if self.is_promotion(from, to) && self.moves_from(from).contains(&to) {
+            Some(vec!['q', 'r', 'b', 'n'])
+        } else {
+            None
+        }
+    }
+
+    /// Internal helper function returning whether moving from one position to the other would promote a pawn,
+    /// meaning a White pawn reaching rank 8 or a Black pawn reaching rank 1. Doesn't check that the move is legal.
+    fn is_promotion(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        match self.board[from.0][from.1] {
+            Piece::Pawn(Colour::White) => to.0 == 0,
+            Piece::Pawn(Colour::Black) => to.0 == 7,
+            _ => false,
+        }
+    }
+
+    /// Returns the piece that a pawn of the given colour promotes to, falling back to a queen for kinds that can't be promoted to.
+    /// The given kind is used if there is one, otherwise the kind selected for that colour.
+    fn promotion_piece(&self, colour: Colour, kind: Option<PieceKind>) -> Piece {
+        let selected = if colour == Colour::White { self.white_promotion } else { self.black_promotion };
+        match kind.unwrap_or(selected) {
+            PieceKind::Rook => Piece::Rook(colour),
+            PieceKind::Bishop => Piece::Bishop(colour),
+            PieceKind::Knight => Piece::Knight(colour),
+            _ => Piece::Queen(colour),
+        }
+    }
+
+    /// Returns the piece placement of the board, the first field of a FEN-notated string.
+    pub fn board_fen(&self) -> String {
+        use std::char;
+
+        let mut fen: String = "".to_string();
+        for x in 0..8 {
+            let mut rank: String = "".to_string();
+            let mut empties = 0;
+            for y in 0..8 {
+                match piece_char(self.board[x][y]) {
+                    None => empties += 1,
+                    Some(_char) => {
+                        if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); empties = 0; }
+                        rank.push(_char);
+                    },
+                }
+            }
+            if empties > 0 {rank.push(char::from_digit(empties, 10).unwrap()); }
+            if x != 7 { rank.push('/'); }
+            fen.push_str(&rank);
+        }
+
+        fen
+    }
+
+    /// Returns the state of the game as a string in FEN-notation.
+    pub fn get_fen(&self) -> String {
+        let mut fen = self.get_fen_short();
+        fen.push(' ');
+        fen.push_str(&self.halfmove_clock.to_string());
+        fen.push(' ');
+        fen.push_str(&self.turn.to_string());
+        fen
+    }
+
+    /// Returns the first four fields of the FEN-notated string given by `get_fen`: the piece placement, the side to move,
+    /// the castling rights and the en-passant square, leaving out the move clocks.
+    /// Positions with the same short FEN count as the same position for threefold repetition.
+    pub fn get_fen_short(&self) -> String {
+        let mut fen = self.board_fen();
+
+        if self.current_turn == Colour::White {
+            fen.push_str(" w ");
+        } else {
+            fen.push_str(" b ");
+        }
+
+        if self.castlings.0 {fen.push('K')}
+        if self.castlings.1 {fen.push('Q')}
+        if self.castlings.2 {fen.push('k')}
+        if self.castlings.3 {fen.push('q')}
+        if self.castlings == (false, false, false, false) {fen.push('-')}
+
+        let x = self.en_passant_square.0;
+        let y = self.en_passant_square.1;
+
+        fen.push(' ');
+        match y {
             0 => {fen.push('a'); 
             fen.push(char::from_digit(8 - x as u32, 10).unwrap());
             },
@@ -282,129 +1263,1859 @@ impl Game {
             },
             _ => {fen.push('-')}
         }
-        fen.push_str(&" ");
-        fen.push_str(&self.halfmove_clock.to_string());
-        fen.push_str(&" ");
-        fen.push_str(&self.turn.to_string());
-        fen
+        fen
+    }
+
+    /// Returns the current position as a line of Extended Position Description: the four fields of `get_fen_short`,
+    /// followed by each operation as its opcode and operand ended by a semicolon, such as `bm e4;` or `id "test";`.
+    /// Operands are written as given, so string operands have to include their quotes.
+    pub fn to_epd(&self, ops: &[(&str, &str)]) -> String {
+        let mut epd = self.get_fen_short();
+        for (opcode, operand) in ops {
+            epd.push(' ');
+            epd.push_str(opcode);
+            if !operand.is_empty() {
+                epd.push(' ');
+                epd.push_str(operand);
+            }
+            epd.push(';');
+        }
+        epd
+    }
+
+    /// Returns the FEN of the current position as the path segment used by Lichess analysis links, with every space replaced by an underscore,
+    /// such that "https://lichess.org/analysis/" followed by it opens the position.
+    pub fn to_lichess_path(&self) -> String {
+        self.get_fen().replace(' ', "_")
+    }
+
+    /// Returns the FEN-notated string of the current position as given by `get_fen`, but with the given side to move
+    /// and no en-passant square, as it no longer applies once the turn is handed over. The game itself is left unchanged.
+    pub fn fen_with_turn(&self, white_to_move: bool) -> String {
+        let mut fields = self.get_fen().split(' ').map(String::from).collect::<Vec<String>>();
+        fields[1] = if white_to_move { "w" } else { "b" }.to_string();
+        fields[3] = "-".to_string();
+        fields.join(" ")
+    }
+
+    /// Returns whether a FEN-notated string describes the current position, comparing only the board, the side to move,
+    /// the castling rights and the en-passant square, so that the move clocks may differ. Returns false if the FEN can't be read.
+    pub fn same_position_as(&self, fen: &str) -> bool {
+        match Game::from_fen(fen) {
+            Ok(other) => other.get_fen_short() == self.get_fen_short(),
+            Err(_) => false,
+        }
+    }
+
+    /// Takes a string in the form "\<square\> \<square\>", moving from the first square to the second.
+    /// Also updates relevant game-tracking variables, such as the halfmove-clock, castlings and the en-passant square.
+    /// If the move ends the game, the state is the first of checkmate, stalemate, the seventy-five-move rule, fivefold repetition
+    /// and insufficient material that applies. The fifty-move rule and threefold repetition only end the game once claimed with `claim_draw`.
+    /// Returns the new game-state, or None if the move isn't legal; use `try_take_turn` to find out why a move was rejected.
+    pub fn take_turn(&mut self, mov: String) -> Option<GameState> {
+        self.try_take_turn(&mov).ok()
+    }
+
+    /// Functions the same as take_turn, but returns the reason the move was rejected if it isn't legal.
+    /// The game is left unchanged when an error is returned, and input that isn't two squares separated by a single space,
+    /// as checked by `is_valid_square`, is rejected with `MoveError::MalformedInput`.
+    /// Once the game has ended, every move is rejected with `MoveError::GameOver`.
+    /// In Crazyhouse, a piece is dropped from the hand with a move such as "N@e5" or "P@d4".
+    pub fn try_take_turn(&mut self, mov: &str) -> Result<GameState, MoveError> {
+        if let Some((piece, square)) = mov.split_once('@') {
+            return self.try_drop(piece, square);
+        }
+        let movs = mov.split(' ').collect::<Vec<&str>>();
+        if movs.len() != 2 || !movs.iter().all(|square| Game::is_valid_square(square)) {
+            return Err(MoveError::MalformedInput);
+        }
+        self.try_make_move(convert_square(movs[0]), convert_square(movs[1]), None)
+    }
+
+    /// Returns whether a string is a single square in chess notation, a file from a to h followed by a rank from 1 to 8, such as "e4".
+    /// Anything else, including surrounding whitespace and capital letters, is rejected.
+    pub fn is_valid_square(square: &str) -> bool {
+        let mut chars = square.chars();
+        matches!((chars.next(), chars.next(), chars.next()), (Some('a'..='h'), Some('1'..='8'), None))
+    }
+
+    /// Internal helper function playing a move in UCI notation, such as "e2e4" or "e7e8q", or in the form taken by `take_turn`.
+    /// A promotion letter on a move that isn't a promotion is ignored.
+    fn try_take_turn_uci(&mut self, mov: &str) -> Result<GameState, MoveError> {
+        let chars = mov.chars().filter(|_char| *_char != ' ').collect::<Vec<char>>();
+        if !(4..=5).contains(&chars.len()) {
+            return Err(MoveError::Notation);
+        }
+        let from = chars[0..2].iter().collect::<String>();
+        let to = chars[2..4].iter().collect::<String>();
+        if !Game::is_valid_square(&from) || !Game::is_valid_square(&to) {
+            return Err(MoveError::Notation);
+        }
+        let promotion = match chars.get(4) {
+            Some(_char) => match piece_from_char(_char.to_ascii_uppercase()).and_then(|piece| piece.kind()) {
+                Some(kind @ (PieceKind::Queen | PieceKind::Rook | PieceKind::Bishop | PieceKind::Knight)) => Some(kind),
+                _ => return Err(MoveError::Notation),
+            },
+            None => None,
+        };
+        self.try_make_move(convert_square(&from), convert_square(&to), promotion)
+    }
+
+    /// Internal helper function checking that a move is legal before playing it with `make_move`,
+    /// and calling the function set with `set_on_move` once it has been played.
+    fn try_make_move(&mut self, from: (usize, usize), to: (usize, usize), promotion: Option<PieceKind>) -> Result<GameState, MoveError> {
+        if self.is_over() { return Err(MoveError::GameOver); }
+        if self.board[from.0][from.1] == Piece::Empty { return Err(MoveError::NoPiece); }
+        if self.board[from.0][from.1].get_colour().unwrap() != &self.current_turn { return Err(MoveError::WrongColour); }
+        let valids = self.board[from.0][from.1].get_valid_moves(from, &self.board, self.en_passant_square, self.castlings, self.current_turn);
+        if !valids.contains(&to) { return Err(MoveError::IllegalMove); }
+
+        let game_state = self.make_move(from, to, promotion);
+        self.notify_move(game_state);
+        Ok(game_state)
+    }
+
+    /// Internal helper function checking that a piece can be dropped from the hand of the player whose turn it is
+    /// before dropping it with `make_drop`. Only Crazyhouse games allow drops.
+    ///
+    /// # Arguments
+    ///
+    /// * `piece`: The letter of the piece to drop, such as "N".
+    /// * `square`: The square to drop it on, in chess notation.
+    fn try_drop(&mut self, piece: &str, square: &str) -> Result<GameState, MoveError> {
+        let square_is_valid = Game::is_valid_square(square);
+        let mut chars = piece.chars();
+        let kind = match (chars.next().and_then(|_char| piece_from_char(_char.to_ascii_uppercase())), chars.next()) {
+            (Some(piece), None) if square_is_valid => piece.kind().unwrap(),
+            _ => return Err(MoveError::Notation),
+        };
+        if self.variant != Variant::Crazyhouse { return Err(MoveError::IllegalMove); }
+        if self.is_over() { return Err(MoveError::GameOver); }
+        if !self.hand(self.current_turn.is_white()).contains(&kind) { return Err(MoveError::NoPiece); }
+        let to = convert_square(square);
+        if !self.is_legal_drop(kind, to) { return Err(MoveError::IllegalMove); }
+
+        let game_state = self.make_drop(kind, to);
+        self.notify_move(game_state);
+        Ok(game_state)
+    }
+
+    /// Internal helper function returning whether the game has ended, so that no more moves can be played.
+    fn is_over(&self) -> bool {
+        matches!(self.game_state, GameState::Checkmate | GameState::Stalemate | GameState::Draw(_) | GameState::Timeout(_) | GameState::Resigned(_))
+    }
+
+    /// Internal helper function finishing a move played through `try_take_turn`, by forgetting the moves `goto_ply` could step
+    /// forward through and calling the function set with `set_on_move`.
+    fn notify_move(&mut self, game_state: GameState) {
+        self.future.clear();
+        let record = *self.history.last().unwrap();
+        if let Some(callback) = self.on_move.0.get_mut().unwrap() {
+            callback(&MoveOutcome {
+                from: square_name(record.from),
+                to: square_name(record.to),
+                promotion: record.promotion,
+                game_state,
+            });
+        }
+    }
+
+    /// Returns the pieces in the hand of the given colour, in the order they were captured.
+    /// Always empty unless the game is played as Crazyhouse.
+    pub fn hand(&self, colour_is_white: bool) -> Vec<PieceKind> {
+        if colour_is_white { self.hands.0.clone() } else { self.hands.1.clone() }
+    }
+
+    /// Internal helper function returning the hand of the given colour for changing it.
+    fn hand_mut(&mut self, colour: Colour) -> &mut Vec<PieceKind> {
+        if colour == Colour::White { &mut self.hands.0 } else { &mut self.hands.1 }
+    }
+
+    /// Internal helper function returning whether the player whose turn it is may drop a piece of the given kind on the given position:
+    /// the square must be empty, pawns can't be dropped on the first or last rank, and the drop can't leave their king in check.
+    /// Doesn't check that the piece is in their hand.
+    fn is_legal_drop(&self, kind: PieceKind, to: (usize, usize)) -> bool {
+        if self.board[to.0][to.1] != Piece::Empty || (kind == PieceKind::Pawn && (to.0 == 0 || to.0 == 7)) {
+            return false;
+        }
+        // Any piece standing on the square blocks the same lines to the king.
+        let mut next = self.clone_position();
+        next.board[to.0][to.1] = Piece::Pawn(self.current_turn);
+        !next.is_king_threatened(self.current_turn)
+    }
+
+    /// Internal helper function returning whether the player whose turn it is has any legal drop.
+    fn has_legal_drop(&self) -> bool {
+        let hand = if self.current_turn.is_white() { &self.hands.0 } else { &self.hands.1 };
+        hand.iter().any(|&kind| (0..8).any(|x| (0..8).any(|y| self.is_legal_drop(kind, (x, y)))))
+    }
+
+    /// Internal helper function dropping a piece of the given kind from the hand of the player whose turn it is onto the given position,
+    /// without checking that the drop is legal. Updates the game-tracking variables and the history, and returns the new game-state.
+    fn make_drop(&mut self, kind: PieceKind, to: (usize, usize)) -> GameState {
+        self.positions.push(self.position_key());
+        let colour = self.current_turn;
+        let hand = self.hand_mut(colour);
+        if let Some(idx) = hand.iter().position(|held| *held == kind) {
+            hand.remove(idx);
+        }
+        let piece = match kind {
+            PieceKind::King => Piece::King(colour),
+            PieceKind::Queen => Piece::Queen(colour),
+            PieceKind::Rook => Piece::Rook(colour),
+            PieceKind::Bishop => Piece::Bishop(colour),
+            PieceKind::Knight => Piece::Knight(colour),
+            PieceKind::Pawn => Piece::Pawn(colour),
+        };
+        let record = MoveRecord {
+            from: to,
+            to,
+            moved: piece,
+            captured: Piece::Empty,
+            captured_square: to,
+            promotion: None,
+            castlings: self.castlings,
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            game_state: self.game_state,
+            gives_check: false,
+            castling_rook: None,
+            dropped: true,
+            captured_promoted: false,
+        };
+        self.board[to.0][to.1] = piece;
+        self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        self.en_passant_square = (8, 8);
+        self.end_turn(record)
+    }
+
+    /// Sets a function to be called after every move played with `take_turn` or `try_take_turn`, replacing any set before.
+    /// It isn't called for rejected moves. The function is dropped by `reset`, and copies of the game don't call it.
+    /// The function must be `Send` so that games can still be moved between threads, such as by a server handling each game
+    /// on its own task; an observer kept in an `Rc<RefCell<_>>` has to be shared as an `Arc<Mutex<_>>` instead.
+    pub fn set_on_move<F: FnMut(&MoveOutcome) + Send + 'static>(&mut self, f: F) {
+        self.on_move = MoveCallback(Mutex::new(Some(Box::new(f))));
+    }
+
+    /// Sets the policy choosing the piece every pawn promotes to from now on, replacing any set before.
+    /// Copies of the game, including those made by `clone_position`, keep the policy, while `reset` goes back to `StandardPromotion`.
+    pub fn set_promotion_policy<P: PromotionPolicy + 'static>(&mut self, policy: P) {
+        self.promotion_policy = PromotionRule(Arc::new(policy));
+    }
+
+    /// Resets the game to the standard starting position, as a new game, dropping the function set with `set_on_move`.
+    pub fn reset(&mut self) {
+        *self = Game::new();
+    }
+
+    /// Internal helper function moving the piece on one position to the other, without checking that the move is legal.
+    /// Updates the game-tracking variables and the history like `try_take_turn`, and returns the new game-state.
+    /// A promoting pawn becomes the given kind of piece, or the one selected for its colour if None is given.
+    fn make_move(&mut self, from: (usize, usize), to: (usize, usize), promotion: Option<PieceKind>) -> GameState {
+        self.positions.push(self.position_key());
+        let mut record = MoveRecord {
+            from,
+            to,
+            moved: self.board[from.0][from.1],
+            captured: self.board[to.0][to.1],
+            captured_square: to,
+            promotion: None,
+            castlings: self.castlings,
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            game_state: self.game_state,
+            gives_check: false,
+            castling_rook: None,
+            dropped: false,
+            captured_promoted: false,
+        };
+
+        self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        let promotes = self.is_promotion(from, to);
+
+        let cur_piece = self.board[from.0][from.1];
+        match cur_piece {
+            Piece::King(colour) => {
+                record.castling_rook = match self.castling_side(from, to) {
+                    Some(true) => Some(((from.0, 7), (from.0, 5))),
+                    Some(false) => Some(((from.0, 0), (from.0, 3))),
+                    None => None,
+                };
+                if let Some((rook_from, rook_to)) = record.castling_rook {
+                    self.board[rook_from.0][rook_from.1] = Piece::Empty;
+                    self.board[rook_to.0][rook_to.1] = Piece::Rook(colour);
+                }
+                if colour == Colour::White {
+                    self.castlings.0 = false;
+                    self.castlings.1 = false;
+                } else {
+                    self.castlings.2 = false;
+                    self.castlings.3 = false;
+                }
+            },
+            Piece::Pawn(_colour) => {
+                if to == self.en_passant_square {
+                    record.captured_square = (from.0, to.1);
+                    record.captured = self.board[from.0][to.1];
+                    match self.en_passant_square.0 {
+                        5 => { 
+                            self.board[self.en_passant_square.0 - 1][self.en_passant_square.1] = Piece::Empty;
+                        }
+                        2 => {
+                            self.board[self.en_passant_square.0 + 1][self.en_passant_square.1] = Piece::Empty;
+                        }
+                        _ => panic!()
+                    }
+                }
+                self.halfmove_clock = 0;
+            },
+            _ => (),
+        }
+
+        if from.0 <= 6 && self.board[from.0][from.1] == Piece::Pawn(Colour::Black) && to.0 == from.0 + 2 {
+            self.en_passant_square = (from.0 + 1, from.1);
+        } else if from.0 >= 2 && self.board[from.0][from.1] == Piece::Pawn(Colour::White) && to.0 == from.0 - 2 {
+            self.en_passant_square = (from.0 - 1, from.1);
+        } else {
+            self.en_passant_square = (8, 8);
+        }
+        
+        match from {
+            (0, 0) => self.castlings.3 = false,
+            (0, 7) => self.castlings.2 = false,
+            (7, 0) => self.castlings.1 = false,
+            (7, 7) => self.castlings.0 = false,
+            _ => ()
+        }
+        match to {
+            (0, 0) => self.castlings.3 = false,
+            (0, 7) => self.castlings.2 = false,
+            (7, 0) => self.castlings.1 = false,
+            (7, 7) => self.castlings.0 = false,
+            _ => ()
+        }
+
+        if self.board[to.0][to.1] != Piece::Empty {
+            self.halfmove_clock = 0;
+        }
+        if self.variant == Variant::Crazyhouse {
+            if let Some(kind) = record.captured.kind() {
+                record.captured_promoted = self.promoted.remove(&record.captured_square);
+                let kind = if record.captured_promoted { PieceKind::Pawn } else { kind };
+                self.hand_mut(self.current_turn).push(kind);
+            }
+            if self.promoted.remove(&from) {
+                self.promoted.insert(to);
+            }
+        }
+
+        self.board[to.0][to.1] = self.board[from.0][from.1];
+        self.board[from.0][from.1] = Piece::Empty;
+
+        if promotes {
+            let requested = self.promotion_piece(self.current_turn, promotion).kind().unwrap_or(PieceKind::Queen);
+            let kind = self.promotion_policy.0.choose(self.current_turn.is_white(), &square_name(from), &square_name(to), requested);
+            self.board[to.0][to.1] = self.promotion_piece(self.current_turn, Some(kind));
+            record.promotion = self.board[to.0][to.1].kind();
+            if self.variant == Variant::Crazyhouse {
+                self.promoted.insert(to);
+            }
+        }
+
+        self.end_turn(record)
+    }
+
+    /// Internal helper function finishing a move once the board has been changed: passes the turn to the other player,
+    /// adds the move to the history and sets the new game-state, which is returned.
+    fn end_turn(&mut self, record: MoveRecord) -> GameState {
+        if self.current_turn == Colour::Black {
+            self.turn = self.turn.saturating_add(1);
+        }
+        self.current_turn = self.current_turn.opposite();
+
+        self.history.push(record);
+        self.game_state = self.position_state();
+        self.history.last_mut().unwrap().gives_check = self.is_king_threatened(self.current_turn);
+        self.game_state
+    }
+
+    /// Plays a sequence of moves, each in the form taken by `take_turn`.
+    /// If any move is rejected, the game is left as it was before the call,
+    /// and the index of the rejected move is returned together with the reason.
+    /// The moves are tried on a copy of the game first, so the function set with `set_on_move` only hears of them if all are played.
+    pub fn apply_moves(&mut self, moves: &[&str]) -> Result<(), (usize, MoveError)> {
+        let mut trial = self.clone();
+        for (idx, mov) in moves.iter().enumerate() {
+            trial.try_take_turn(mov).map_err(|error| (idx, error))?;
+        }
+        for mov in moves {
+            self.try_take_turn(mov).unwrap();
+        }
+        Ok(())
+    }
+
+    /// Returns whether moving from one square to the other castles: Some(true) for castling kingside, Some(false) for queenside,
+    /// and None for any other move, or if either string isn't a square. Castling is recognised as a king moving two squares
+    /// along the rank from its home square, without checking that the move is legal.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The square moved from, in chess notation.
+    /// * `to`: The square moved to, in chess notation.
+    pub fn is_castling_move(&self, from: &str, to: &str) -> Option<bool> {
+        if !Game::is_valid_square(from) || !Game::is_valid_square(to) {
+            return None;
+        }
+        self.castling_side(convert_square(from), convert_square(to))
+    }
+
+    /// Returns why the given colour can't castle to the given side right now, such as "king has moved", "no rook on h1",
+    /// "f1 is occupied", "king is in check" or "f1 is attacked", or None if castling is legal apart from whose turn it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `colour_is_white`: Whether to look at White's (true) or Black's (false) castling.
+    /// * `kingside`: Whether to look at castling kingside (true) or queenside (false).
+    pub fn castling_blocked_reason(&self, colour_is_white: bool, kingside: bool) -> Option<String> {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        let home = if colour_is_white { 7 } else { 0 };
+        let right = match (colour_is_white, kingside) {
+            (true, true) => self.castlings.0,
+            (true, false) => self.castlings.1,
+            (false, true) => self.castlings.2,
+            (false, false) => self.castlings.3,
+        };
+        let rook = (home, if kingside { 7 } else { 0 });
+        let between: &[usize] = if kingside { &[5, 6] } else { &[3, 2, 1] };
+
+        if self.board[home][4] != Piece::King(colour) {
+            return Some("king has moved".to_string());
+        }
+        if self.board[rook.0][rook.1] != Piece::Rook(colour) {
+            return Some(format!("no rook on {}", square_name(rook)));
+        }
+        if !right {
+            return Some("castling right has been lost".to_string());
+        }
+        if let Some(&file) = between.iter().find(|&&file| self.board[home][file] != Piece::Empty) {
+            return Some(format!("{} is occupied", square_name((home, file))));
+        }
+        if !self.get_attackers((home, 4), colour.opposite()).is_empty() {
+            return Some("king is in check".to_string());
+        }
+        // The king passes over and lands on the first two squares; on the queenside the third only has to be empty.
+        if let Some(&file) = between[..2].iter().find(|&&file| !self.get_attackers((home, file), colour.opposite()).is_empty()) {
+            return Some(format!("{} is attacked", square_name((home, file))));
+        }
+        None
+    }
+
+    /// Returns whether moving from one square to the other is an en-passant capture: a pawn of the player whose turn it is
+    /// moving one square diagonally forward onto the empty en-passant square. Doesn't check that the move is otherwise legal.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The square moved from, in chess notation.
+    /// * `to`: The square moved to, in chess notation.
+    pub fn is_en_passant(&self, from: &str, to: &str) -> bool {
+        if !Game::is_valid_square(from) || !Game::is_valid_square(to) {
+            return false;
+        }
+        let from = convert_square(from);
+        let to = convert_square(to);
+        let forward = match self.board[from.0][from.1] {
+            Piece::Pawn(colour) if colour == self.current_turn => if colour.is_white() { from.0.checked_sub(1) } else { Some(from.0 + 1) },
+            _ => return false,
+        };
+        to == self.en_passant_square && forward == Some(to.0) && from.1.abs_diff(to.1) == 1 && self.board[to.0][to.1] == Piece::Empty
+    }
+
+    /// Returns the kind of the legal move between two squares if it is castling, an en-passant capture or a promotion,
+    /// and None for any other move, including illegal ones. Promotions are to the piece selected for the player whose turn it is.
+    pub fn special_move_kind(&self, from: &str, to: &str) -> Option<MoveKind> {
+        if !Game::is_valid_square(from) || !Game::is_valid_square(to) {
+            return None;
+        }
+        let from = convert_square(from);
+        let to = convert_square(to);
+        let selected = self.promotion_piece(self.current_turn, None).kind();
+        let mov = self.all_legal_moves().into_iter()
+            .find(|mov| mov.from == from && mov.to == to && (mov.promotion.is_none() || mov.promotion == selected))?;
+        match mov.kind {
+            MoveKind::Castle(_) | MoveKind::EnPassant | MoveKind::Promotion(_) | MoveKind::PromotionCapture(_) => Some(mov.kind),
+            _ => None,
+        }
+    }
+
+    /// Internal helper function returning which side a move between two positions castles to, as `is_castling_move`.
+    fn castling_side(&self, from: (usize, usize), to: (usize, usize)) -> Option<bool> {
+        let home = match self.board[from.0][from.1] {
+            Piece::King(Colour::White) => (7, 4),
+            Piece::King(Colour::Black) => (0, 4),
+            _ => return None,
+        };
+        if from != home || to.0 != home.0 {
+            return None;
+        }
+        match to.1 {
+            6 => Some(true),
+            2 => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Sets the game to the position after the given number of half-moves, where 0 is the position the history starts from,
+    /// for analysis boards with a move slider. Moves stepped back over can be stepped forward through again,
+    /// until a new move is played or `undo_move` is called. Returns an error if the game has no such position.
+    #[allow(clippy::result_unit_err)]
+    pub fn goto_ply(&mut self, ply: usize) -> Result<(), ()> {
+        if ply > self.history.len() + self.future.len() {
+            return Err(());
+        }
+        let mut future = std::mem::take(&mut self.future);
+        while self.history.len() > ply {
+            future.push(*self.history.last().unwrap());
+            self.undo_move();
+        }
+        while self.history.len() < ply {
+            let record = future.pop().unwrap();
+            if record.dropped {
+                self.make_drop(record.moved.kind().unwrap(), record.to);
+            } else {
+                self.make_move(record.from, record.to, record.promotion);
+            }
+        }
+        self.future = future;
+        Ok(())
+    }
+
+    /// Takes back the most recently played move, restoring the board and all game-tracking variables.
+    /// Returns the restored game-state, or None if there is no move to take back.
+    pub fn undo_move(&mut self) -> Option<GameState> {
+        self.future.clear();
+        let record = self.history.pop()?;
+        self.positions.pop();
+        let colour = *record.moved.get_colour().unwrap();
+
+        if record.dropped {
+            self.board[record.to.0][record.to.1] = Piece::Empty;
+            self.hand_mut(colour).push(record.moved.kind().unwrap());
+        } else {
+            self.board[record.to.0][record.to.1] = Piece::Empty;
+            self.board[record.captured_square.0][record.captured_square.1] = record.captured;
+            self.board[record.from.0][record.from.1] = record.moved;
+            if let (Variant::Crazyhouse, Some(kind)) = (self.variant, record.captured.kind()) {
+                let kind = if record.captured_promoted { PieceKind::Pawn } else { kind };
+                let hand = self.hand_mut(colour);
+                if let Some(idx) = hand.iter().rposition(|held| *held == kind) {
+                    hand.remove(idx);
+                }
+            }
+            if self.promoted.remove(&record.to) && record.promotion.is_none() {
+                self.promoted.insert(record.from);
+            }
+            if record.captured_promoted {
+                self.promoted.insert(record.captured_square);
+            }
+        }
+
+        if let Some((rook_from, rook_to)) = record.castling_rook {
+            self.board[rook_from.0][rook_from.1] = self.board[rook_to.0][rook_to.1];
+            self.board[rook_to.0][rook_to.1] = Piece::Empty;
+        }
+
+        self.castlings = record.castlings;
+        self.en_passant_square = record.en_passant_square;
+        self.halfmove_clock = record.halfmove_clock;
+        self.game_state = record.game_state;
+
+        if self.current_turn == Colour::White {
+            self.turn = self.turn.saturating_sub(1);
+        }
+        self.current_turn = self.current_turn.opposite();
+
+        Some(self.game_state)
+    }
+
+    /// Ends the game with the given colour resigning, and their opponent winning. Returns the new game-state.
+    pub fn resign(&mut self, colour_is_white: bool) -> GameState {
+        let winner = if colour_is_white { Colour::Black } else { Colour::White };
+        self.game_state = GameState::Resigned(winner);
+        self.game_state
+    }
+
+    /// Returns the result of the game as written in PGN: "1-0" or "0-1" for a win by White or Black,
+    /// "1/2-1/2" for a draw, or "*" while the game is still going.
+    pub fn result(&self) -> &'static str {
+        let winner = match self.game_state {
+            GameState::Checkmate => self.current_turn.opposite(),
+            GameState::Stalemate | GameState::Draw(_) => return "1/2-1/2",
+            GameState::Timeout(colour) | GameState::Resigned(colour) => colour,
+            _ => return "*",
+        };
+        if winner.is_white() { "1-0" } else { "0-1" }
+    }
+
+    /// Returns the number of half-moves played in this game, as recorded in its history. A game set up from a FEN-notated string
+    /// starts counting from 0 whatever its full-move number, and moves stepped back over with `goto_ply` aren't counted.
+    pub fn total_plies(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Returns the number of half-moves played since the last capture or pawn move, the count used by the fifty-move rule.
+    pub fn plies_since_capture(&self) -> usize {
+        self.halfmove_clock
+    }
+
+    /// Returns the number of full moves left before the fifty-move rule draws the game, counting a half-move left over as a full move,
+    /// or 0 once it already does.
+    pub fn moves_until_fifty_move_draw(&self) -> usize {
+        100usize.saturating_sub(self.halfmove_clock).div_ceil(2)
+    }
+
+    /// Returns the squares the most recent move was made from and to, or None if no move has been played.
+    pub fn last_move_squares(&self) -> Option<(String, String)> {
+        let record = self.history.last()?;
+        Some((square_name(record.from), square_name(record.to)))
+    }
+
+    /// Returns the square the most recent move promoted on and the FEN letter of the piece it promoted to,
+    /// such as ("e8", 'N') or ("a1", 'q'), or None if it was not a promotion.
+    pub fn last_promotion(&self) -> Option<(String, char)> {
+        let record = self.history.last()?;
+        let colour = *record.moved.get_colour()?;
+        let piece = self.promotion_piece(colour, Some(record.promotion?));
+        Some((square_name(record.to), piece_char(piece)?))
+    }
+
+    /// Returns the moves played so far in ICCF numeric notation, separated by spaces.
+    /// Each square is written as its file (a = 1 to h = 8) followed by its rank,
+    /// with a promotion written as a fifth digit: 1 for a queen, 2 for a rook, 3 for a bishop and 4 for a knight.
+    pub fn move_log_iccf(&self) -> String {
+        self.history.iter().map(|record| {
+            let mut mov = String::new();
+            for pos in [record.from, record.to].iter() {
+                mov.push_str(&(pos.1 + 1).to_string());
+                mov.push_str(&(8 - pos.0).to_string());
+            }
+            match record.promotion {
+                Some(PieceKind::Queen) => mov.push('1'),
+                Some(PieceKind::Rook) => mov.push('2'),
+                Some(PieceKind::Bishop) => mov.push('3'),
+                Some(PieceKind::Knight) => mov.push('4'),
+                _ => (),
+            }
+            mov
+        }).collect::<Vec<String>>().join(" ")
+    }
+
+    /// Starts tracking time for both players, turning the game into a timed game.
+    ///
+    /// # Arguments
+    ///
+    /// * `white_ms`: The time White starts with, in milliseconds.
+    /// * `black_ms`: The time Black starts with, in milliseconds.
+    /// * `increment_ms`: The time added to a player's clock after each of their moves, in milliseconds.
+    pub fn set_time_control(&mut self, white_ms: u64, black_ms: u64, increment_ms: u64) {
+        self.time_control = Some(TimeControl { white_ms, black_ms, increment_ms });
+    }
+
+    /// Returns the time the given colour has left in milliseconds, or None if the game isn't timed.
+    pub fn time_remaining(&self, colour_is_white: bool) -> Option<u64> {
+        let clocks = self.time_control?;
+        if colour_is_white { Some(clocks.white_ms) } else { Some(clocks.black_ms) }
+    }
+
+    /// Charges the time spent on the most recent move to the player who made it, then adds the increment.
+    /// If the time spent uses up the rest of their clock, the game ends with their opponent winning on time,
+    /// unless the move already ended the game some other way, such as by checkmate.
+    /// Returns the new game-state, or None if the game isn't timed.
+    ///
+    /// # Arguments
+    ///
+    /// * `elapsed_ms`: The time the move took, in milliseconds.
+    pub fn record_move_time(&mut self, elapsed_ms: u64) -> Option<GameState> {
+        let mut clocks = self.time_control?;
+        let mover = self.current_turn.opposite();
+        let remaining = if mover == Colour::White { &mut clocks.white_ms } else { &mut clocks.black_ms };
+        if elapsed_ms >= *remaining {
+            *remaining = 0;
+            if !self.is_over() {
+                self.game_state = GameState::Timeout(self.current_turn);
+            }
+        } else {
+            *remaining = *remaining - elapsed_ms + clocks.increment_ms;
+        }
+        self.time_control = Some(clocks);
+        Some(self.game_state)
+    }
+
+    /// Returns whether moving from one square to the other checkmates the opponent, without playing the move.
+    /// Returns false if the move isn't legal.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The square moved from, in chess notation.
+    /// * `to`: The square moved to, in chess notation.
+    /// * `promotion`: The piece a pawn promotes to, as one of the chars accepted by `select_promotion`, or None to use the player's selection.
+    pub fn is_mate_move(&self, from: &str, to: &str, promotion: Option<char>) -> bool {
+        let promotion = match promotion {
+            Some(_char) => match piece_from_char(_char.to_ascii_uppercase()).and_then(|piece| piece.kind()) {
+                Some(kind @ (PieceKind::Queen | PieceKind::Rook | PieceKind::Bishop | PieceKind::Knight)) => Some(kind),
+                _ => return false,
+            },
+            None => None,
+        };
+        if !Game::is_valid_square(from) || !Game::is_valid_square(to) {
+            return false;
+        }
+        let from = convert_square(from);
+        let to = convert_square(to);
+        let promotion = if self.is_promotion(from, to) { self.promotion_piece(self.current_turn, promotion).kind() } else { None };
+        let mov = match self.all_legal_moves().into_iter().find(|mov| mov.from == from && mov.to == to && mov.promotion == promotion) {
+            Some(mov) => mov,
+            None => return false,
+        };
+        let mut game = self.clone();
+        game.play_move(mov) == Ok(GameState::Checkmate)
+    }
+
+    /// Returns how many of the moves played by the given colour put the opponent in check, including checkmate.
+    pub fn check_count(&self, colour_is_white: bool) -> usize {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        self.history.iter().filter(|record| record.gives_check && record.moved.get_colour() == Some(&colour)).count()
+    }
+
+    /// Returns the FEN of every position of the game in order, from the position the game started from through the current one.
+    /// The earlier positions are found by taking back moves on a copy of the game.
+    pub fn position_fens(&self) -> Vec<String> {
+        let mut previous = self.clone();
+        let mut fens = vec![previous.get_fen()];
+        while previous.undo_move().is_some() {
+            fens.push(previous.get_fen());
+        }
+        fens.reverse();
+        fens
+    }
+
+    /// Returns the FEN of the position the game started from, or None if it started from the standard starting position,
+    /// whatever its move clocks. This is the position last loaded with `set_state_from_fen` or a similar method,
+    /// or the position copied by `clone_position`.
+    pub fn started_from_fen(&self) -> Option<String> {
+        self.start_fen.clone()
+    }
+
+    /// Internal helper function returning the FEN of the current position, or None if it is the standard starting position
+    /// apart from the move clocks, for remembering as the position the game started from.
+    fn custom_fen(&self) -> Option<String> {
+        let fen = self.get_fen();
+        if fen.split(' ').take(4).eq(START_FEN.split(' ').take(4)) { None } else { Some(fen) }
+    }
+
+    /// Returns the FEN of the position before the most recent move, or None if no move has been played.
+    /// The game itself is left untouched; the move is taken back on a copy.
+    pub fn previous_fen(&self) -> Option<String> {
+        let mut previous = self.clone();
+        previous.undo_move()?;
+        Some(previous.get_fen())
+    }
+
+    /// Returns every legal move of the player whose turn it is.
+    /// Rather than trying every move on a copy of the board, the pieces checking the king and the pieces pinned to it
+    /// are found once, and the moves of every piece but the king are checked against them. King moves are checked
+    /// against the enemy attacks on each square they go to.
+    pub fn all_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let king = match self.find_king(self.current_turn) {
+            Some(king) => king,
+            None => return self.get_all_moves(),
+        };
+        for to in self.safe_king_moves(king) {
+            self.push_moves(&mut moves, king, to);
+        }
+
+        let checkers = self.get_attackers(king, self.current_turn.opposite());
+        if checkers.len() > 1 {
+            return moves;
+        }
+        let check_mask = checkers.first().map(|&checker| {
+            let mut mask = vec![checker];
+            if matches!(self.board[checker.0][checker.1], Piece::Queen(_) | Piece::Rook(_) | Piece::Bishop(_)) {
+                mask.extend(squares_between(&square_name(king), &square_name(checker)).unwrap().iter().map(|square| convert_square(square)));
+            }
+            mask
+        });
+        let pins = self.get_pins(king, self.current_turn);
+
+        for x in 0..8 {
+            for y in 0..8 {
+                let piece = self.board[x][y];
+                if (x, y) == king || piece.get_colour() != Some(&self.current_turn) { continue; }
+                let pin_ray = pins.get(&(x, y));
+                for to in piece.get_pseudo_legal_moves((x, y), &self.board, self.en_passant_square, self.castlings) {
+                    let legal = if matches!(piece, Piece::Pawn(_)) && to == self.en_passant_square && to.1 != y {
+                        !clean_moves((x, y), &self.board, vec![to], self.current_turn).is_empty()
+                    } else {
+                        check_mask.as_ref().is_none_or(|mask| mask.contains(&to)) && pin_ray.is_none_or(|ray| ray.contains(&to))
+                    };
+                    if legal {
+                        self.push_moves(&mut moves, (x, y), to);
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    /// Internal helper function returning the moves of the king of the player whose turn it is, on the given position,
+    /// that don't end on a square the enemy attacks. The king is taken off the board while looking,
+    /// so that it can't shield the squares behind it from the piece checking it.
+    fn safe_king_moves(&self, king: (usize, usize)) -> Vec<(usize, usize)> {
+        let colour = self.current_turn;
+        let mut board = self.board.clone();
+        board[king.0][king.1] = Piece::Empty;
+        Piece::King(colour).get_pseudo_legal_moves(king, &self.board, self.en_passant_square, self.castlings)
+            .into_iter()
+            .filter(|&to| {
+                let held = board[to.0][to.1];
+                board[to.0][to.1] = Piece::King(colour);
+                let attacked = (0..8).any(|x| (0..8).any(|y| {
+                    board[x][y].get_colour() == Some(&colour.opposite()) && board[x][y].get_threatened_squares((x, y), &board).contains(&to)
+                }));
+                board[to.0][to.1] = held;
+                !attacked
+            })
+            .collect()
+    }
+
+    /// Returns the legal moves of the player whose turn it is one at a time, checking each for legality only once it is asked for,
+    /// so that a search can stop early without generating the whole list. Yields the same moves as `all_legal_moves`,
+    /// though not necessarily in the same order.
+    pub fn legal_moves_iter(&self) -> impl Iterator<Item = Move> + '_ {
+        (0..64).map(|idx| (idx / 8, idx % 8))
+            .filter(move |pos| self.board[pos.0][pos.1].get_colour() == Some(&self.current_turn))
+            .flat_map(move |from| {
+                self.board[from.0][from.1].get_pseudo_legal_moves(from, &self.board, self.en_passant_square, self.castlings)
+                    .into_iter()
+                    .filter(move |&to| !clean_moves(from, &self.board, vec![to], self.current_turn).is_empty())
+                    .flat_map(move |to| {
+                        let mut moves = Vec::new();
+                        self.push_moves(&mut moves, from, to);
+                        moves
+                    })
+            })
+    }
+
+    /// Returns every legal move of the player whose turn it is in a fixed order meant for search: captures first,
+    /// taking the most valuable victim with the least valuable attacker first, then promotions, then the other moves.
+    /// Moves that are alike by that are ordered by the square moved from and then the square moved to, in board order from a8 to h1.
+    pub fn ordered_legal_moves(&self) -> Vec<Move> {
+        let mut moves = self.all_legal_moves();
+        moves.sort_by_key(|mov| {
+            let attacker = self.board[mov.from.0][mov.from.1].value();
+            let category = match mov.kind {
+                MoveKind::Capture | MoveKind::EnPassant | MoveKind::PromotionCapture(_) => 0,
+                MoveKind::Promotion(_) => 1,
+                _ => 2,
+            };
+            let victim = match mov.kind {
+                MoveKind::EnPassant => 1,
+                _ => self.board[mov.to.0][mov.to.1].value(),
+            };
+            (category, -victim, attacker, mov.from, mov.to)
+        });
+        moves
+    }
+
+    /// Returns whether every legal move of the player whose turn it is promotes a pawn, so the promotion piece has to be chosen
+    /// whatever is played. Returns false if there are no legal moves at all.
+    pub fn must_promote(&self) -> bool {
+        let moves = self.all_legal_moves();
+        !moves.is_empty() && moves.iter().all(|mov| mov.promotion.is_some())
+    }
+
+    /// Returns every legal capture of the player whose turn it is, including en-passant captures and promotions that capture,
+    /// in the order given by `ordered_legal_moves`.
+    pub fn legal_captures(&self) -> Vec<Move> {
+        self.ordered_legal_moves().into_iter()
+            .filter(|mov| matches!(mov.kind, MoveKind::Capture | MoveKind::EnPassant | MoveKind::PromotionCapture(_)))
+            .collect()
+    }
+
+    /// Returns the captures from `legal_captures` that win material once every recapture on the square has been played out,
+    /// meaning their static exchange evaluation is positive.
+    pub fn winning_captures(&self) -> Vec<Move> {
+        self.legal_captures().into_iter().filter(|mov| self.static_exchange(*mov) > 0).collect()
+    }
+
+    /// Internal helper function returning the material, in pawns, won by a capture when both players keep recapturing on its square
+    /// with their least valuable piece for as long as that gains them something.
+    fn static_exchange(&self, mov: Move) -> i32 {
+        let mut gain = match mov.kind {
+            MoveKind::EnPassant => 1,
+            _ => self.board[mov.to.0][mov.to.1].value(),
+        };
+        if let Some(kind) = mov.promotion {
+            gain += self.promotion_piece(self.current_turn, Some(kind)).value() - 1;
+        }
+        let mut after = self.clone_position();
+        if after.play_move(mov).is_err() {
+            return 0;
+        }
+        let recapture = after.all_legal_moves().into_iter()
+            .filter(|reply| reply.to == mov.to)
+            .min_by_key(|reply| after.board[reply.from.0][reply.from.1].value());
+        match recapture {
+            Some(reply) => gain - after.static_exchange(reply).max(0),
+            None => gain,
+        }
+    }
+
+    /// Returns every legal move of the player whose turn it is in UCI notation, such as "e2e4", "e7e8q" or "e1g1" for castling,
+    /// in the order given by `all_legal_moves`.
+    pub fn legal_moves_uci(&self) -> Vec<String> {
+        self.all_legal_moves().into_iter().map(|mov| {
+            let mut uci = square_name(mov.from) + &square_name(mov.to);
+            match mov.promotion {
+                Some(PieceKind::Queen) => uci.push('q'),
+                Some(PieceKind::Rook) => uci.push('r'),
+                Some(PieceKind::Bishop) => uci.push('b'),
+                Some(PieceKind::Knight) => uci.push('n'),
+                _ => (),
+            }
+            uci
+        }).collect()
+    }
+
+    /// Returns the legal moves that get the player whose turn it is out of check, for "find the defense" puzzles.
+    /// Returns an empty list if that player isn't in check, or is checkmated.
+    pub fn check_escapes(&self) -> Vec<Move> {
+        if !self.is_king_threatened(self.current_turn) {
+            return Vec::new();
+        }
+        self.all_legal_moves()
+    }
+
+    /// Returns the legal moves of the player whose turn it is that put the opponent in check, discovered checks included.
+    pub fn checking_moves(&self) -> Vec<Move> {
+        self.all_legal_moves().into_iter().filter(|mov| self.gives_check(mov)).collect()
+    }
+
+    /// Internal helper function returning whether playing the given move would put the opponent in check.
+    /// The move is played on a copy of the position, so it is expected to be legal.
+    fn gives_check(&self, mov: &Move) -> bool {
+        let mut next = self.clone_position();
+        next.make_move(mov.from, mov.to, mov.promotion);
+        next.is_king_threatened(next.current_turn)
+    }
+
+    /// Returns the squares the piece on the given square can legally move to, meant for highlighting drop targets while dragging a piece.
+    /// The legal moves of the position are generated on the first call after the position changes and reused until it changes again,
+    /// so this can be called for every square without generating the moves each time.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The square moved from, in chess notation.
+    pub fn legal_destinations(&self, from: &str) -> Vec<String> {
+        if !Game::is_valid_square(from) {
+            return Vec::new();
+        }
+        let from = convert_square(from);
+        let mut cache = self.legal_moves_cache.0.lock().unwrap();
+        let is_current = cache.as_ref().is_some_and(|cached| {
+            cached.board == self.board && cached.current_turn == self.current_turn
+                && cached.castlings == self.castlings && cached.en_passant_square == self.en_passant_square
+        });
+        if !is_current {
+            *cache = Some(CachedMoves {
+                board: self.board.clone(),
+                current_turn: self.current_turn,
+                castlings: self.castlings,
+                en_passant_square: self.en_passant_square,
+                moves: self.all_legal_moves(),
+            });
+        }
+
+        let mut destinations = Vec::new();
+        for mov in cache.as_ref().unwrap().moves.iter().filter(|mov| mov.from == from) {
+            let square = square_name(mov.to);
+            if !destinations.contains(&square) {
+                destinations.push(square);
+            }
+        }
+        destinations
+    }
+
+    /// Returns whether the player whose turn it is has exactly one legal move. Each promotion piece counts as its own move.
+    /// The pieces are looked at one by one, stopping as soon as a second move is found.
+    pub fn is_forced(&self) -> bool {
+        let mut count = 0;
+        for x in 0..8 {
+            for y in 0..8 {
+                for to in self.moves_from((x, y)) {
+                    count += if self.is_promotion((x, y), to) { 4 } else { 1 };
+                    if count > 1 {
+                        return false;
+                    }
+                }
+            }
+        }
+        count == 1
+    }
+
+    /// Returns the squares of the pieces of the player whose turn it is that can legally move to the given square.
+    /// Returns an empty list if the string isn't a square.
+    ///
+    /// # Arguments
+    ///
+    /// * `to`: The square moved to, in chess notation.
+    pub fn pieces_that_can_move_to(&self, to: &str) -> Vec<String> {
+        if !Game::is_valid_square(to) {
+            return Vec::new();
+        }
+        let to = convert_square(to);
+        let mut origins = Vec::new();
+        for mov in self.all_legal_moves().iter().filter(|mov| mov.to == to) {
+            let square = square_name(mov.from);
+            if !origins.contains(&square) {
+                origins.push(square);
+            }
+        }
+        origins
+    }
+
+    /// Returns every piece of the given colour with the squares it can legally move to, in board order from a8 to h1.
+    /// Pieces without legal moves are included with an empty list. If it isn't that colour's turn,
+    /// the moves are those they would have if it were, with no en-passant capture available.
+    pub fn legal_moves_grouped(&self, colour_is_white: bool) -> Vec<(String, Vec<String>)> {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        let mut position = self.clone_position();
+        if colour != self.current_turn {
+            position.current_turn = colour;
+            position.en_passant_square = (8, 8);
+        }
+        let moves = position.all_legal_moves();
+
+        let mut grouped = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                if self.board[x][y].get_colour() != Some(&colour) { continue; }
+                let mut destinations = Vec::new();
+                for mov in moves.iter().filter(|mov| mov.from == (x, y)) {
+                    let square = square_name(mov.to);
+                    if !destinations.contains(&square) {
+                        destinations.push(square);
+                    }
+                }
+                grouped.push((square_name((x, y)), destinations));
+            }
+        }
+        grouped
+    }
+
+    /// Returns every piece of the given colour with the number of squares it can legally move to, as given by `legal_moves_grouped`.
+    pub fn mobility_map(&self, colour_is_white: bool) -> Vec<(String, usize)> {
+        self.legal_moves_grouped(colour_is_white)
+            .into_iter()
+            .map(|(square, destinations)| (square, destinations.len()))
+            .collect()
+    }
+
+    /// Returns the number of legal moves of White minus that of Black, counting the squares each piece can move to as `mobility_map` does,
+    /// in a single pass over the board. The side not to move is counted as if it were their turn, with no en-passant capture available,
+    /// so their king is never counted as capturable even if it stands in check.
+    pub fn mobility_difference(&self) -> i32 {
+        let mut difference = 0;
+        for x in 0..8 {
+            for y in 0..8 {
+                let piece = self.board[x][y];
+                let colour = match piece.get_colour() {
+                    Some(colour) => *colour,
+                    None => continue,
+                };
+                let en_passant_square = if colour == self.current_turn { self.en_passant_square } else { (8, 8) };
+                let count = piece.get_valid_moves((x, y), &self.board, en_passant_square, self.castlings, colour).len() as i32;
+                difference += if colour.is_white() { count } else { -count };
+            }
+        }
+        difference
+    }
+
+    /// Internal helper function returning every legal move of the player whose turn it is, by trying each move of each of their pieces on a copy of the board.
+    fn get_all_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                for to in self.moves_from((x, y)) {
+                    self.push_moves(&mut moves, (x, y), to);
+                }
+            }
+        }
+        moves
+    }
+
+    /// Returns the direction from the king to the piece pinning the piece on the given square, if that piece is pinned to its own king.
+    /// The direction is given in the coordinates of the board, as a step in rank index followed by a step in file index,
+    /// so a piece pinned by a rook further up the same file as a White king gives (-1, 0). Returns None for empty squares, unpinned pieces
+    /// and strings that aren't squares.
+    ///
+    /// # Arguments
+    ///
+    /// * `square`: The square of the piece, in chess notation.
+    pub fn pin_direction(&self, square: &str) -> Option<(i32, i32)> {
+        if !Game::is_valid_square(square) {
+            return None;
+        }
+        let pos = convert_square(square);
+        let colour = *self.board[pos.0][pos.1].get_colour()?;
+        let king = self.find_king(colour)?;
+        if !self.get_pins(king, colour).contains_key(&pos) {
+            return None;
+        }
+        Some(((pos.0 as i32 - king.0 as i32).signum(), (pos.1 as i32 - king.1 as i32).signum()))
+    }
+
+    /// Internal helper function returning the pieces of the given colour that are pinned to their king on the given position,
+    /// each with the squares it may still move to: those between the king and the pinning piece, and the pinning piece itself.
+    fn get_pins(&self, king: (usize, usize), colour: Colour) -> HashMap<(usize, usize), Vec<(usize, usize)>> {
+        let mut pins = HashMap::new();
+        for &(dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (-1, 1), (1, -1), (1, 1)].iter() {
+            let diagonal = dx != 0 && dy != 0;
+            let mut ray = Vec::new();
+            let mut pinned = None;
+            let mut x = king.0 as i32 + dx;
+            let mut y = king.1 as i32 + dy;
+            while (0..8).contains(&x) && (0..8).contains(&y) {
+                let piece = self.board[x as usize][y as usize];
+                ray.push((x as usize, y as usize));
+                if piece.get_colour() == Some(&colour) {
+                    if pinned.is_some() { break; }
+                    pinned = Some((x as usize, y as usize));
+                } else if piece != Piece::Empty {
+                    let pins_along_ray = match piece {
+                        Piece::Queen(_) => true,
+                        Piece::Rook(_) => !diagonal,
+                        Piece::Bishop(_) => diagonal,
+                        _ => false,
+                    };
+                    if let (Some(pinned), true) = (pinned, pins_along_ray) {
+                        pins.insert(pinned, ray);
+                    }
+                    break;
+                }
+                x += dx;
+                y += dy;
+            }
+        }
+        pins
+    }
+
+    /// Counts the move paths of the given number of half-moves from the current position, a standard test of move generation
+    /// that is compared against the known counts for well-studied positions.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth`: The number of half-moves to play along each path.
+    pub fn perft(&self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.all_legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut count = 0;
+        for mov in moves {
+            let mut next = self.clone_position();
+            next.make_move(mov.from, mov.to, mov.promotion);
+            count += next.perft(depth - 1);
+        }
+        count
+    }
+
+    /// Finds the legal move that a move in SAN, such as "Nbd7", "exd8=Q+" or "O-O", describes in the current position,
+    /// without playing it. Returns `MoveError::Notation` if the string can't be read as SAN,
+    /// `MoveError::Ambiguous` if more than one legal move matches it, and `MoveError::IllegalMove` if none does.
+    /// A promotion that doesn't name a piece, such as "e8" or "exd8", promotes to the piece set with `set_default_promotion`.
+    pub fn resolve_san(&self, san: &str) -> Result<Move, MoveError> {
+        let san = san.trim_end_matches(|_char| "+#!?".contains(_char));
+        let moves = self.all_legal_moves();
+        let home = if self.current_turn.is_white() { 7 } else { 0 };
+        let castling = match san {
+            "O-O" | "0-0" => Some(6),
+            "O-O-O" | "0-0-0" => Some(2),
+            _ => None,
+        };
+        if let Some(file) = castling {
+            if self.board[home][4] != Piece::King(self.current_turn) { return Err(MoveError::IllegalMove); }
+            return moves.into_iter().find(|mov| mov.from == (home, 4) && mov.to == (home, file)).ok_or(MoveError::IllegalMove);
+        }
+
+        let (san, promotion) = match san.split_once('=') {
+            Some((san, piece)) => {
+                let mut chars = piece.chars();
+                let kind = chars.next().and_then(piece_from_char).ok_or(MoveError::Notation)?.kind();
+                if chars.next().is_some() { return Err(MoveError::Notation); }
+                (san, kind)
+            },
+            None => (san, None),
+        };
+        let mut chars = san.chars().filter(|_char| *_char != 'x').collect::<Vec<char>>();
+        let kind = match chars.first() {
+            Some(_char) if _char.is_ascii_uppercase() => {
+                let kind = piece_from_char(*_char).ok_or(MoveError::Notation)?.kind();
+                chars.remove(0);
+                kind
+            },
+            _ => Some(PieceKind::Pawn),
+        };
+        if chars.len() < 2 { return Err(MoveError::Notation); }
+        let (hint, square) = chars.split_at(chars.len() - 2);
+        let square = square.iter().collect::<String>();
+        if !Game::is_valid_square(&square) { return Err(MoveError::Notation); }
+        let to = convert_square(&square);
+        let default = self.promotion_piece(self.current_turn, None).kind();
+
+        let mut candidates = moves.into_iter().filter(|mov| {
+            mov.to == to && (mov.promotion == promotion || (promotion.is_none() && mov.promotion == default)) && self.board[mov.from.0][mov.from.1].kind() == kind
+                && hint.iter().all(|_char| square_name(mov.from).contains(*_char))
+        });
+        let mov = candidates.next().ok_or(MoveError::IllegalMove)?;
+        if candidates.next().is_some() { Err(MoveError::Ambiguous) } else { Ok(mov) }
+    }
+
+    /// Plays a move given in SAN, such as "Nf3" or "O-O", as found by `resolve_san`, returning the new game-state.
+    /// The game is left unchanged when an error is returned.
+    pub fn take_turn_san(&mut self, san: &str) -> Result<GameState, MoveError> {
+        let mov = self.resolve_san(san)?;
+        self.play_move(mov)
+    }
+
+    /// Internal helper function playing a move as generated by `all_legal_moves`, promoting to the piece it names
+    /// without changing the promotion either player has selected.
+    fn play_move(&mut self, mov: Move) -> Result<GameState, MoveError> {
+        self.try_make_move(mov.from, mov.to, mov.promotion)
+    }
+
+    /// Internal helper function adding the move between two positions to a list of moves, classified by its kind,
+    /// once for every piece that can be promoted to if the move is a promotion.
+    fn push_moves(&self, moves: &mut Vec<Move>, from: (usize, usize), to: (usize, usize)) {
+        let captures = self.board[to.0][to.1] != Piece::Empty;
+        if self.is_promotion(from, to) {
+            for promotion in [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight].iter() {
+                let kind = if captures { MoveKind::PromotionCapture(*promotion) } else { MoveKind::Promotion(*promotion) };
+                moves.push(Move { from, to, promotion: Some(*promotion), ep_capture: None, kind });
+            }
+            return;
+        }
+
+        let is_pawn = matches!(self.board[from.0][from.1], Piece::Pawn(_));
+        let (kind, ep_capture) = if is_pawn && to == self.en_passant_square && to.1 != from.1 {
+            (MoveKind::EnPassant, Some((from.0, to.1)))
+        } else if let Some(kingside) = self.castling_side(from, to) {
+            (MoveKind::Castle(kingside), None)
+        } else if captures {
+            (MoveKind::Capture, None)
+        } else if is_pawn && from.0.abs_diff(to.0) == 2 {
+            (MoveKind::DoublePawnPush, None)
+        } else {
+            (MoveKind::Quiet, None)
+        };
+        moves.push(Move { from, to, promotion: None, ep_capture, kind });
+    }
+
+    /// Internal helper function returning the position of the king of the given colour, if there is one on the board.
+    fn find_king(&self, colour: Colour) -> Option<(usize, usize)> {
+        for x in 0..8 {
+            for y in 0..8 {
+                if self.board[x][y] == Piece::King(colour) {
+                    return Some((x, y));
+                }
+            }
+        }
+        None
+    }
+
+    /// Internal helper function returning the legal moves of the piece on the given position,
+    /// or no moves if the piece doesn't belong to the player whose turn it is.
+    fn moves_from(&self, pos: (usize, usize)) -> Vec<(usize, usize)> {
+        let piece = self.board[pos.0][pos.1];
+        if piece.get_colour() != Some(&self.current_turn) {
+            return Vec::new();
+        }
+        piece.get_valid_moves(pos, &self.board, self.en_passant_square, self.castlings, self.current_turn)
+    }
+
+    /// Returns the squares of all pieces of the given colour that attack the given square, whatever stands on it.
+    /// Returns an empty list if the string isn't a square.
+    ///
+    /// # Arguments
+    ///
+    /// * `square`: The attacked square in chess notation, such as e4.
+    /// * `by_white`: Whether to look for White (true) or Black (false) attackers.
+    pub fn attackers_of(&self, square: &str, by_white: bool) -> Vec<String> {
+        if !Game::is_valid_square(square) {
+            return Vec::new();
+        }
+        let colour = if by_white { Colour::White } else { Colour::Black };
+        self.get_attackers(convert_square(square), colour)
+            .into_iter()
+            .map(square_name)
+            .collect()
+    }
+
+    /// Returns every attack made by the pieces of the given colour as a pair of the attacking piece's square and the attacked square,
+    /// for drawing arrows over the board. Sliding pieces attack each square up to and including the first piece in their way,
+    /// so squares holding pieces of their own colour are counted as attacked too.
+    pub fn attack_lines(&self, by_white: bool) -> Vec<(String, String)> {
+        let colour = if by_white { Colour::White } else { Colour::Black };
+        let mut lines = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                for attacker in self.get_attackers((x, y), colour) {
+                    lines.push((square_name(attacker), square_name((x, y))));
+                }
+            }
+        }
+        lines
+    }
+
+    /// Returns the squares the piece on the given square covers, split into the squares of friendly pieces it defends
+    /// and the empty or enemy squares it attacks. Sliding pieces stop at the first piece in their way, and pawns only cover
+    /// the squares diagonally in front of them. Both lists are empty for an empty square, or a string that isn't a square.
+    ///
+    /// # Arguments
+    ///
+    /// * `square`: The square of the piece, in chess notation.
+    pub fn coverage(&self, square: &str) -> (Vec<String>, Vec<String>) {
+        let mut defended = Vec::new();
+        let mut attacked = Vec::new();
+        if !Game::is_valid_square(square) {
+            return (defended, attacked);
+        }
+        let pos = convert_square(square);
+        let colour = match self.board[pos.0][pos.1].get_colour() {
+            Some(colour) => *colour,
+            None => return (defended, attacked),
+        };
+        for x in 0..8 {
+            for y in 0..8 {
+                if (x, y) == pos || !self.get_attackers((x, y), colour).contains(&pos) { continue; }
+                if self.board[x][y].get_colour() == Some(&colour) {
+                    defended.push(square_name((x, y)));
+                } else {
+                    attacked.push(square_name((x, y)));
+                }
+            }
+        }
+        (defended, attacked)
+    }
+
+    /// Returns the number of White pieces attacking each square minus the number of Black pieces attacking it,
+    /// indexed like the board, with rank 8 first and the a-file first within each rank. Defended pieces count as attacked.
+    pub fn control_map(&self) -> [[i32; 8]; 8] {
+        let mut map = [[0; 8]; 8];
+        for (x, rank) in map.iter_mut().enumerate() {
+            for (y, control) in rank.iter_mut().enumerate() {
+                *control = self.get_attackers((x, y), Colour::White).len() as i32 - self.get_attackers((x, y), Colour::Black).len() as i32;
+            }
+        }
+        map
+    }
+
+    /// Returns every piece of the given colour that attacks two or more enemy pieces at once, together with the squares of those pieces.
+    /// At least one of the targets has to be worth winning: the king, a piece worth more than the attacker, or a piece no enemy defends.
+    pub fn forks(&self, colour_is_white: bool) -> Vec<(String, Vec<String>)> {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        let mut forks = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                let attacker = self.board[x][y];
+                if attacker.get_colour() != Some(&colour) { continue; }
+                let (_, targets) = self.coverage(&square_name((x, y)));
+                let targets = targets.into_iter().filter(|target| {
+                    let (tx, ty) = convert_square(target);
+                    self.board[tx][ty].get_colour() == Some(&colour.opposite())
+                }).collect::<Vec<String>>();
+                let worth_winning = targets.iter().any(|target| {
+                    let pos = convert_square(target);
+                    let target = self.board[pos.0][pos.1];
+                    target.kind() == Some(PieceKind::King)
+                        || target.value() > attacker.value()
+                        || self.get_attackers(pos, colour.opposite()).is_empty()
+                });
+                if targets.len() >= 2 && worth_winning {
+                    forks.push((square_name((x, y)), targets));
+                }
+            }
+        }
+        forks
+    }
+
+    /// Returns the squares the king of the given colour can legally move to, including castling,
+    /// whether or not it is that colour's turn. Returns an empty list if there is no such king.
+    pub fn king_moves(&self, colour_is_white: bool) -> Vec<String> {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        match self.find_king(colour) {
+            Some(king) => Piece::King(colour).get_valid_moves(king, &self.board, self.en_passant_square, self.castlings, colour)
+                .into_iter()
+                .map(square_name)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns whether the piece on the given square is defended by another piece of its own colour.
+    /// An empty square, or a string that isn't a square, is never defended.
+    pub fn is_defended(&self, square: &str) -> bool {
+        if !Game::is_valid_square(square) {
+            return false;
+        }
+        let pos = convert_square(square);
+        match self.board[pos.0][pos.1].get_colour() {
+            Some(colour) => !self.get_attackers(pos, *colour).is_empty(),
+            None => false,
+        }
+    }
+
+    /// Returns the squares of the given colour's pieces that are attacked by the enemy and either not defended,
+    /// or attacked by a piece worth less than themselves, so that defending them doesn't save the material.
+    /// Kings are never reported.
+    pub fn hanging_pieces(&self, colour_is_white: bool) -> Vec<String> {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        let enemy = colour.opposite();
+        let mut hanging = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                let piece = self.board[x][y];
+                if piece.get_colour() != Some(&colour) || piece == Piece::King(colour) { continue; }
+                let attackers = self.get_attackers((x, y), enemy);
+                if attackers.is_empty() { continue; }
+                let cheapest_attacker = attackers.iter().map(|pos| self.board[pos.0][pos.1].value()).min().unwrap();
+                if cheapest_attacker < piece.value() || self.get_attackers((x, y), colour).is_empty() {
+                    hanging.push(square_name((x, y)));
+                }
+            }
+        }
+        hanging
+    }
+
+    /// Returns the legal moves of the player whose turn it is that leave none of their pieces hanging, as given by `hanging_pieces`
+    /// in the position after the move, in the order given by `all_legal_moves`. This can be empty if every move leaves something en prise.
+    pub fn safe_moves(&self) -> Vec<Move> {
+        let colour_is_white = self.current_turn.is_white();
+        self.all_legal_moves().into_iter().filter(|mov| {
+            let mut after = self.clone_position();
+            after.play_move(*mov).is_ok() && after.hanging_pieces(colour_is_white).is_empty()
+        }).collect()
+    }
+
+    /// Returns the squares in the given colour's half of the board (ranks 1 to 4 for white, 5 to 8 for black)
+    /// that are attacked by more enemy pieces than they are defended by.
+    pub fn weak_squares(&self, colour_is_white: bool) -> Vec<String> {
+        let ranks = if colour_is_white { 4..8 } else { 0..4 };
+        let mut weak = Vec::new();
+        for x in ranks {
+            for y in 0..8 {
+                let square = square_name((x, y));
+                if self.attackers_of(&square, !colour_is_white).len() > self.attackers_of(&square, colour_is_white).len() {
+                    weak.push(square);
+                }
+            }
+        }
+        weak
+    }
+
+    /// Internal helper function returning the positions of all pieces of the given colour attacking the given position.
+    /// The position is treated as holding an enemy piece, so that pieces defending a friendly piece are counted too.
+    fn get_attackers(&self, pos: (usize, usize), colour: Colour) -> Vec<(usize, usize)> {
+        let mut board = self.board.clone();
+        board[pos.0][pos.1] = Piece::Pawn(colour.opposite());
+        let mut attackers = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                if board[x][y].get_colour() == Some(&colour) && board[x][y].get_threatened_squares((x, y), &board).contains(&pos) {
+                    attackers.push((x, y));
+                }
+            }
+        }
+        attackers
+    }
+
+    /// Returns the number of White pieces minus the number of Black pieces of each kind, from queens down to pawns.
+    /// Kings are left out, as each side always has one.
+    pub fn material_breakdown(&self) -> Vec<(PieceKind, i32)> {
+        [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight, PieceKind::Pawn].iter().map(|&kind| {
+            let mut difference = 0;
+            for piece in self.board.iter().flatten().filter(|piece| piece.kind() == Some(kind)) {
+                difference += if piece.get_colour() == Some(&Colour::White) { 1 } else { -1 };
+            }
+            (kind, difference)
+        }).collect()
+    }
+
+    /// Returns the material of White minus the material of Black, counted in pawns.
+    pub fn material_balance(&self) -> i32 {
+        let mut balance = 0;
+        for rank in self.board.iter() {
+            for piece in rank.iter() {
+                match piece.get_colour() {
+                    Some(Colour::White) => balance += piece.value(),
+                    Some(Colour::Black) => balance -= piece.value(),
+                    None => (),
+                }
+            }
+        }
+        balance
+    }
+
+    /// Returns how far the game has moved towards the endgame, from 0 with all pieces on the board to 256 with only kings and pawns.
+    /// Knights and bishops count 1 each, rooks 2 and queens 4 towards the 24 of the starting position, and pawns don't count.
+    /// Meant for tapering between opening and endgame evaluations; a position with extra pieces from promotion counts as 0.
+    pub fn phase_value(&self) -> u32 {
+        let mut material = 0;
+        for rank in self.board.iter() {
+            for piece in rank.iter() {
+                material += match piece {
+                    Piece::Knight(_) | Piece::Bishop(_) => 1,
+                    Piece::Rook(_) => 2,
+                    Piece::Queen(_) => 4,
+                    _ => 0,
+                };
+            }
+        }
+        (24 - material.min(24)) * 256 / 24
+    }
+
+    /// Returns the stage of the game, judged from the pieces other than pawns left on the board by `phase_value`:
+    /// the opening while at most a pair of minor pieces is gone, and the endgame once at most a rook and a minor piece each are left.
+    pub fn game_phase(&self) -> Phase {
+        match self.phase_value() {
+            0..=21 => Phase::Opening,
+            22..=181 => Phase::Middlegame,
+            _ => Phase::Endgame,
+        }
+    }
+
+    /// Returns a simple static evaluation of the position in centipawns, positive when White is better.
+    /// Combines the material balance, the difference in the number of legal moves of each side,
+    /// small bonuses for castling rights kept and pawns on d4, e4, d5 and e5, weighted by the constants above,
+    /// and the difference in `king_shield` between the players.
+    pub fn evaluate(&self) -> i32 {
+        let rights = |kingside: bool, queenside: bool| kingside as i32 + queenside as i32;
+        let mut central_pawns = 0;
+        for &(x, y) in [(3, 3), (3, 4), (4, 3), (4, 4)].iter() {
+            match self.board[x][y] {
+                Piece::Pawn(Colour::White) => central_pawns += 1,
+                Piece::Pawn(Colour::Black) => central_pawns -= 1,
+                _ => (),
+            }
+        }
+
+        self.material_balance() * MATERIAL_WEIGHT
+            + self.mobility_difference() * MOBILITY_WEIGHT
+            + (rights(self.castlings.0, self.castlings.1) - rights(self.castlings.2, self.castlings.3)) * CASTLING_RIGHT_BONUS
+            + central_pawns * CENTRAL_PAWN_BONUS
+            + self.king_shield(true) - self.king_shield(false)
+    }
+
+    /// Returns the distance between the two kings in king moves, the larger of the number of ranks and files between them,
+    /// or 0 if either king is missing.
+    pub fn king_distance(&self) -> usize {
+        match (self.find_king(Colour::White), self.find_king(Colour::Black)) {
+            (Some(white), Some(black)) => white.0.abs_diff(black.0).max(white.1.abs_diff(black.1)),
+            _ => 0,
+        }
+    }
+
+    /// Returns a king-safety score for the given player from the pawns sheltering their king, weighted by the constants above:
+    /// on the king's file and each neighbouring one, a pawn of their own one or two squares in front of the king scores a bonus,
+    /// while a file without one is penalised, and more so if it has no pawns at all. Returns 0 if the player has no king.
+    pub fn king_shield(&self, colour_is_white: bool) -> i32 {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        let (king_x, king_y) = match self.find_king(colour) {
+            Some(pos) => pos,
+            None => return 0,
+        };
+        let forward = |steps: i32| {
+            let x = if colour_is_white { king_x as i32 - steps } else { king_x as i32 + steps };
+            if (0..8).contains(&x) { Some(x as usize) } else { None }
+        };
+
+        let mut score = 0;
+        for y in king_y.saturating_sub(1)..=(king_y + 1).min(7) {
+            let own_pawn = |steps: i32| forward(steps).is_some_and(|x| self.board[x][y] == Piece::Pawn(colour));
+            if own_pawn(1) {
+                score += SHIELD_PAWN_BONUS;
+            } else if own_pawn(2) {
+                score += ADVANCED_SHIELD_PAWN_BONUS;
+            } else {
+                score -= MISSING_SHIELD_PAWN_PENALTY;
+                if (0..8).all(|x| self.board[x][y].kind() != Some(PieceKind::Pawn)) {
+                    score -= OPEN_FILE_PENALTY;
+                }
+            }
+        }
+        score
     }
 
-    /// Takes a string in the form "\<square\> \<square\>", moving from the first square to the second.
-    /// Also updates relevant game-tracking variables, such as the halfmove-clock, castlings and the en-passant square.
-    pub fn take_turn(&mut self, mov: String) -> Option<GameState> {
-        let movs = mov.split(" ").collect::<Vec<&str>>();
-        let from = convert_square(movs[0]);
-        let to = convert_square(movs[1]);
+    /// Returns whether the side to move appears to be in zugzwang, where any move makes their position worse than passing would.
+    /// This is only a rough heuristic: each move is scored by `evaluate` after the opponent's best reply, and so is passing the turn,
+    /// and every move has to score at least `ZUGZWANG_THRESHOLD` below passing. Returns false if the side to move is in check,
+    /// has a capture or a checking move, has only their king, or would leave the opponent without a move by passing.
+    pub fn is_in_zugzwang_lite(&self) -> bool {
+        let moves = self.all_legal_moves();
+        let own_pieces = self.board.iter().flatten().filter(|piece| piece.get_colour() == Some(&self.current_turn)).count();
+        if moves.is_empty() || own_pieces < 2 || self.is_king_threatened(self.current_turn) {
+            return false;
+        }
+        if moves.iter().any(|mov| self.board[mov.to.0][mov.to.1] != Piece::Empty || mov.ep_capture.is_some() || self.gives_check(mov)) {
+            return false;
+        }
 
-        self.halfmove_clock = self.halfmove_clock + 1;
+        let mut passed = self.clone_position();
+        passed.current_turn = self.current_turn.opposite();
+        passed.en_passant_square = (8, 8);
+        if passed.all_legal_moves().is_empty() {
+            return false;
+        }
+        passed.game_state = passed.get_game_state_no_recursion();
+        let pass_score = match negamax(&passed, 1, 1, -MATE_SCORE - 1, MATE_SCORE + 1, None) {
+            Some(score) => -score,
+            None => return false,
+        };
 
-        if self.board[from.0][from.1] == Piece::Empty || self.board[from.0][from.1].get_colour().unwrap() != &self.current_turn { return None; }
-        let valids = self.board[from.0][from.1].get_valid_moves(from, &self.board, self.en_passant_square, self.castlings, self.current_turn);
+        moves.into_iter().all(|mov| {
+            let mut child = self.clone_position();
+            if child.play_move(mov).is_err() {
+                return false;
+            }
+            match negamax(&child, 1, 1, -MATE_SCORE - 1, MATE_SCORE + 1, None) {
+                Some(score) => -score <= pass_score - ZUGZWANG_THRESHOLD,
+                None => false,
+            }
+        })
+    }
 
-        if valids.contains(&to) {
-            let cur_piece = self.board[from.0][from.1];
-            match cur_piece {
-                Piece::King(Colour::Black) => {
-                    if to == convert_square("g8") && self.castlings.2 {
-                        self.board[0][7] = Piece::Empty;
-                        self.board[0][5] = Piece::Rook(Colour::Black);
-                    }
-                    if to == convert_square("c8") && self.castlings.3 {
-                        self.board[0][0] = Piece::Empty;
-                        self.board[0][3] = Piece::Rook(Colour::Black);
-                    }
-                    self.castlings.2 = false;
-                    self.castlings.3 = false;
-                },
-                Piece::King(Colour::White) => {
-                    if to == convert_square("g1") && self.castlings.0 {
-                        self.board[7][7] = Piece::Empty;
-                        self.board[7][5] = Piece::Rook(Colour::White);
-                    }
-                    if to == convert_square("c1") && self.castlings.1 {
-                        self.board[7][0] = Piece::Empty;
-                        self.board[7][3] = Piece::Rook(Colour::White);
-                    }
-                    self.castlings.0 = false;
-                    self.castlings.1 = false;
-                },
-                Piece::Pawn(colour) => {
-                    if to == self.en_passant_square {
-                        match self.en_passant_square.0 {
-                            5 => { 
-                                self.board[self.en_passant_square.0 - 1][self.en_passant_square.1] = Piece::Empty;
-                            }
-                            2 => {
-                                self.board[self.en_passant_square.0 + 1][self.en_passant_square.1] = Piece::Empty;
-                            }
-                            _ => panic!()
-                        }
-                    }
-                    self.halfmove_clock = 0;
-                },
-                _ => (),
+    /// Returns the Zobrist hash of the position: the board, the side to move, the castling rights and the en-passant square.
+    /// Positions that are the same by those hash the same, and the keys are fixed, so hashes can be stored between runs.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for x in 0..8 {
+            for y in 0..8 {
+                let piece = self.board[x][y];
+                let kind = match piece.kind() {
+                    Some(PieceKind::King) => 0,
+                    Some(PieceKind::Queen) => 1,
+                    Some(PieceKind::Rook) => 2,
+                    Some(PieceKind::Bishop) => 3,
+                    Some(PieceKind::Knight) => 4,
+                    Some(PieceKind::Pawn) => 5,
+                    None => continue,
+                };
+                let colour = if piece.get_colour() == Some(&Colour::White) { 0 } else { 6 };
+                hash ^= ZOBRIST_KEYS[(kind + colour) * 64 + x * 8 + y];
             }
         }
-
-        if from.0 <= 6 && self.board[from.0][from.1] == Piece::Pawn(Colour::Black) && to.0 == from.0 + 2 {
-            self.en_passant_square = (from.0 + 1, from.1);
-        } else if from.0 >= 2 && self.board[from.0][from.1] == Piece::Pawn(Colour::White) && to.0 == from.0 - 2 {
-            self.en_passant_square = (from.0 - 1, from.1);
-        } else {
-            self.en_passant_square = (8, 8);
+        if self.current_turn == Colour::Black {
+            hash ^= ZOBRIST_KEYS[768];
         }
-        
-        match from {
-            (0, 0) => self.castlings.3 = false,
-            (0, 7) => self.castlings.2 = false,
-            (7, 0) => self.castlings.1 = false,
-            (7, 7) => self.castlings.0 = false,
-            _ => ()
+        let rights = [self.castlings.0, self.castlings.1, self.castlings.2, self.castlings.3];
+        for (idx, right) in rights.iter().enumerate() {
+            if *right {
+                hash ^= ZOBRIST_KEYS[769 + idx];
+            }
         }
-        match to {
-            (0, 0) => self.castlings.3 = false,
-            (0, 7) => self.castlings.2 = false,
-            (7, 0) => self.castlings.1 = false,
-            (7, 7) => self.castlings.0 = false,
-            _ => ()
+        if self.en_passant_square.1 < 8 {
+            hash ^= ZOBRIST_KEYS[773 + self.en_passant_square.1];
+        }
+        hash
+    }
+
+    /// Returns whether the current position has already occurred on a search path, given the `zobrist_hash` of each position on it.
+    /// Engines usually score a position as a draw as soon as it repeats once within the search,
+    /// rather than waiting for the threefold repetition needed in the game itself.
+    pub fn is_repetition_in_search(&self, search_stack: &[u64]) -> bool {
+        search_stack.contains(&self.zobrist_hash())
+    }
+
+    /// Returns the squares occupied by White and by Black pieces as 64-bit boards.
+    /// Squares are numbered from a1 as bit 0 along each rank, so h1 is bit 7, a2 is bit 8 and h8 is bit 63.
+    pub fn occupancy(&self) -> (u64, u64) {
+        (
+            self.bitboard(|piece| piece.get_colour() == Some(&Colour::White)),
+            self.bitboard(|piece| piece.get_colour() == Some(&Colour::Black)),
+        )
+    }
+
+    /// Returns the squares holding pieces of the given kind and colour as a 64-bit board, numbered like in `occupancy`,
+    /// with a1 as bit 0 and h8 as bit 63.
+    pub fn piece_bitboard(&self, kind: PieceKind, colour: Colour) -> u64 {
+        self.bitboard(|piece| piece.kind() == Some(kind) && piece.get_colour() == Some(&colour))
+    }
+
+    /// Internal helper function returning the squares holding a piece for which the given function returns true, as a 64-bit board
+    /// numbered like in `occupancy`.
+    fn bitboard<F: Fn(Piece) -> bool>(&self, includes: F) -> u64 {
+        let mut bitboard = 0;
+        for x in 0..8 {
+            for y in 0..8 {
+                if includes(self.board[x][y]) {
+                    bitboard |= 1 << ((7 - x) * 8 + y);
+                }
+            }
         }
+        bitboard
+    }
 
-        if self.board[to.0][to.1] != Piece::Empty {
-            self.halfmove_clock = 0;
+    /// Returns every square whose piece differs between this game and the other, in board order from a8 to h1,
+    /// with the FEN piece letters on it in this game and in the other, or None where the square is empty.
+    /// Applying the changes to this game's board gives the other's; the rest of the game state isn't compared.
+    pub fn diff(&self, other: &Game) -> Vec<(String, Option<char>, Option<char>)> {
+        let mut changes = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                if self.board[x][y] != other.board[x][y] {
+                    changes.push((square_name((x, y)), piece_char(self.board[x][y]), piece_char(other.board[x][y])));
+                }
+            }
         }
+        changes
+    }
 
-        self.board[to.0][to.1] = self.board[from.0][from.1];
-        self.board[from.0][from.1] = Piece::Empty;
+    /// Returns whether the player whose turn it is has been checkmated by a knight alone, with every square around their king
+    /// taken by their own pieces: a smothered mate.
+    pub fn is_smothered_mate(&self) -> bool {
+        let king = match self.find_king(self.current_turn) {
+            Some(king) => king,
+            None => return false,
+        };
+        let checkers = self.get_attackers(king, self.current_turn.opposite());
+        self.terminal_kind() == Some(TerminalKind::Checkmate)
+            && checkers.iter().all(|pos| matches!(self.board[pos.0][pos.1], Piece::Knight(_)))
+            && king_table()[king.0 * 8 + king.1].iter().all(|pos| self.board[pos.0][pos.1].get_colour() == Some(&self.current_turn))
+    }
 
-        if self.board[to.0][to.1] == Piece::Pawn(Colour::White) && to.0 == 0 {
-            self.board[to.0][to.1] = self.selected_promotion;
+    /// Returns the colour-mirrored position: the board is flipped vertically and every piece changes colour,
+    /// along with the castling rights, the en-passant square and the side to move. The mirrored game has no history.
+    pub fn mirror(&self) -> Game {
+        let mut mirrored = self.clone_position();
+        mirrored.board = self.board.iter().rev().map(|rank| {
+            rank.iter().map(|piece| match *piece {
+                Piece::King(colour) => Piece::King(colour.opposite()),
+                Piece::Queen(colour) => Piece::Queen(colour.opposite()),
+                Piece::Rook(colour) => Piece::Rook(colour.opposite()),
+                Piece::Bishop(colour) => Piece::Bishop(colour.opposite()),
+                Piece::Knight(colour) => Piece::Knight(colour.opposite()),
+                Piece::Pawn(colour) => Piece::Pawn(colour.opposite()),
+                Piece::Empty => Piece::Empty,
+            }).collect()
+        }).collect();
+        mirrored.current_turn = self.current_turn.opposite();
+        mirrored.castlings = (self.castlings.2, self.castlings.3, self.castlings.0, self.castlings.1);
+        if self.en_passant_square.0 < 8 {
+            mirrored.en_passant_square = (7 - self.en_passant_square.0, self.en_passant_square.1);
         }
-        if self.board[to.0][to.1] == Piece::Pawn(Colour::Black) && to.0 == 7 {
-            self.board[to.0][to.1] = self.selected_promotion;
+        mirrored.promoted = self.promoted.iter().map(|&(x, y)| (7 - x, y)).collect();
+        mirrored.white_promotion = self.black_promotion;
+        mirrored.black_promotion = self.white_promotion;
+        if let Some(clocks) = self.time_control {
+            mirrored.time_control = Some(TimeControl { white_ms: clocks.black_ms, black_ms: clocks.white_ms, increment_ms: clocks.increment_ms });
         }
+        mirrored.start_fen = mirrored.custom_fen();
+        mirrored
+    }
 
-        if self.current_turn == Colour::Black {
-            self.turn = self.turn + 1;
-            self.current_turn = Colour::White;
-        } else {
-            self.current_turn = Colour::Black;
+    /// Counts the doubled, isolated and passed pawns of one side.
+    /// A file with n pawns counts as n - 1 doubled pawns, a pawn is isolated if no friendly pawn stands on an adjacent file,
+    /// and a pawn is passed if no enemy pawn stands in front of it on its own or an adjacent file.
+    ///
+    /// # Arguments
+    ///
+    /// * `colour_is_white`: Whether to count the pawns of White (true) or Black (false).
+    pub fn pawn_structure(&self, colour_is_white: bool) -> PawnStructure {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        let mut structure = PawnStructure { doubled: 0, isolated: 0, passed: 0 };
+
+        let mut files = [0; 8];
+        for rank in self.board.iter() {
+            for (y, piece) in rank.iter().enumerate() {
+                if *piece == Piece::Pawn(colour) { files[y] += 1; }
+            }
+        }
+        for count in files.iter() {
+            if *count > 1 { structure.doubled += count - 1; }
         }
 
-        match self.selected_promotion {
-            Piece::Bishop(_colour) => self.selected_promotion = Piece::Bishop(self.current_turn),
-            Piece::Rook(_colour) => self.selected_promotion = Piece::Rook(self.current_turn),
-            Piece::Knight(_colour) => self.selected_promotion = Piece::Knight(self.current_turn),
-            Piece::Queen(_colour) => self.selected_promotion = Piece::Queen(self.current_turn),
-            _ => panic!()
+        for x in 0..8 {
+            for y in 0..8 {
+                if self.board[x][y] != Piece::Pawn(colour) { continue; }
+                if (y.saturating_sub(1)..=(y + 1).min(7)).all(|file| file == y || files[file] == 0) {
+                    structure.isolated += 1;
+                }
+                if self.is_passed_pawn((x, y)) {
+                    structure.passed += 1;
+                }
+            }
         }
+        structure
+    }
 
-        self.game_state = self.get_game_state(true);
-        Some(self.game_state)
+    /// Returns the square of every passed pawn of the given colour, together with the number of ranks it has left to promotion.
+    ///
+    /// # Arguments
+    ///
+    /// * `colour_is_white`: Whether to look at the pawns of White (true) or Black (false).
+    pub fn passed_pawns(&self, colour_is_white: bool) -> Vec<(String, usize)> {
+        let colour = if colour_is_white { Colour::White } else { Colour::Black };
+        let mut passed = Vec::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                if self.board[x][y] == Piece::Pawn(colour) && self.is_passed_pawn((x, y)) {
+                    passed.push((square_name((x, y)), if colour_is_white { x } else { 7 - x }));
+                }
+            }
+        }
+        passed
+    }
+
+    /// Returns whether the move between two squares leaves a pawn of the player making it passed that wasn't before,
+    /// either the pawn moved or another one, such as when a capture removes the last enemy pawn in front of it.
+    /// Returns false if the move isn't legal.
+    pub fn creates_passed_pawn(&self, from: &str, to: &str) -> bool {
+        if !Game::is_valid_square(from) || !Game::is_valid_square(to) {
+            return false;
+        }
+        let colour_is_white = self.current_turn.is_white();
+        let from = convert_square(from);
+        let to = convert_square(to);
+        let before = self.passed_pawns(colour_is_white).into_iter().map(|(square, _)| square).collect::<Vec<String>>();
+        let mut after = self.clone_position();
+        if after.try_make_move(from, to, None).is_err() {
+            return false;
+        }
+        after.passed_pawns(colour_is_white).into_iter().any(|(square, _)| {
+            let origin = if square == square_name(to) { square_name(from) } else { square };
+            !before.contains(&origin)
+        })
+    }
+
+    /// Internal helper function returning whether the pawn on the given position is passed,
+    /// meaning no enemy pawn stands ahead of it on its own file or the files next to it.
+    fn is_passed_pawn(&self, pos: (usize, usize)) -> bool {
+        let colour = match self.board[pos.0][pos.1] {
+            Piece::Pawn(colour) => colour,
+            _ => return false,
+        };
+        let ahead: Vec<usize> = if colour == Colour::White { (0..pos.0).collect() } else { (pos.0 + 1..8).collect() };
+        !ahead.iter().any(|&rank| {
+            (pos.1.saturating_sub(1)..=(pos.1 + 1).min(7)).any(|file| self.board[rank][file] == Piece::Pawn(colour.opposite()))
+        })
     }
 }
 
 /// Enumerable that holds the state of a single piece on the board, with awareness of how it moves and captures.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Piece {
     King(Colour),
     Queen(Colour),
@@ -418,27 +3129,19 @@ pub enum Piece {
 impl Piece {
     /// Functions the same as get_valid_moves, but only returns the surrounding squares for Kings.
     /// Used for making sure there's no endless recursion when checking for checks.
-    fn get_threatened_squares(&self, pos: (usize, usize), board: &Vec<Vec<Piece>>) -> Vec<(usize, usize)> {
+    fn get_threatened_squares(&self, pos: (usize, usize), board: &[Vec<Piece>]) -> Vec<(usize, usize)> {
         match self {
-            Piece::King(_colour) => {
-                let mut moves = Vec::new();
-                for x in 0..3 {
-                    if pos.0 + x == 0 { continue; }
-                    for y in 0..3 {
-                        if pos.1 + y == 0 { continue; }
-                        moves.push((pos.0 + x - 1, pos.1 + y - 1));
-                    }
-                }
-                moves
-            },
+            Piece::King(_colour) => king_table()[pos.0 * 8 + pos.1].clone(),
             Piece::Pawn(_colour) => {
                 let mut moves = Vec::new();
-                if (_colour == &Colour::Black) {
-                    if pos.1 != 0 {
-                        moves.push((pos.0 + 1, pos.1 - 1));
-                    }
-                    if pos.1 != 7 {
-                        moves.push((pos.0 + 1, pos.1 + 1));
+                if _colour == &Colour::Black {
+                    if pos.0 != 7 {
+                        if pos.1 != 0 {
+                            moves.push((pos.0 + 1, pos.1 - 1));
+                        }
+                        if pos.1 != 7 {
+                            moves.push((pos.0 + 1, pos.1 + 1));
+                        }
                     }
                 } else if pos.0 != 0 {
                     if pos.1 != 0 {
@@ -470,35 +3173,26 @@ impl Piece {
     }
 
     /// The public function to return any valid moves for the single piece it is called from. 
-    pub fn get_valid_moves(&self, pos: (usize, usize), board: &Vec<Vec<Piece>>, en_passant_square: (usize, usize), castlings: (bool, bool, bool, bool), turn: Colour) -> Vec<(usize, usize)> {
+    pub fn get_valid_moves(&self, pos: (usize, usize), board: &[Vec<Piece>], en_passant_square: (usize, usize), castlings: (bool, bool, bool, bool), turn: Colour) -> Vec<(usize, usize)> {
+        let moves = self.get_pseudo_legal_moves(pos, board, en_passant_square, castlings);
+        clean_moves(pos, board, moves, turn)
+    }
+
+    /// Functions the same as get_valid_moves, but keeps the moves that would place the player in check.
+    fn get_pseudo_legal_moves(&self, pos: (usize, usize), board: &[Vec<Piece>], en_passant_square: (usize, usize), castlings: (bool, bool, bool, bool)) -> Vec<(usize, usize)> {
         match &self {
             Piece::Empty => Vec::new(),
             Piece::Queen(_colour) => {
                 let mut moves = Vec::new();
                 moves.append(&mut self.get_rook_moves(pos, board));
                 moves.append(&mut self.get_bishop_moves(pos, board));
-                clean_moves(pos, board, moves, turn)
-            },
-            Piece::Rook(_colour) => {
-                let moves = self.get_rook_moves(pos, board);
-                clean_moves(pos, board, moves, turn)
-            },
-            Piece::Bishop(_colour) => {
-                let moves = self.get_bishop_moves(pos, board);
-                clean_moves(pos, board, moves, turn)
-            },
-            Piece::Knight(_colour) => {
-                let moves = self.get_knight_moves(pos, board);
-                clean_moves(pos, board, moves, turn)
-            },
-            Piece::Pawn(_colour) => {
-                let moves = self.get_pawn_moves(pos, board, en_passant_square);
-                clean_moves(pos, board, moves, turn)
-            },
-            Piece::King(_colour) => {
-                let moves = self.get_king_moves(pos, board, castlings);
-                clean_moves(pos, board, moves, turn)
+                moves
             },
+            Piece::Rook(_colour) => self.get_rook_moves(pos, board),
+            Piece::Bishop(_colour) => self.get_bishop_moves(pos, board),
+            Piece::Knight(_colour) => self.get_knight_moves(pos, board),
+            Piece::Pawn(_colour) => self.get_pawn_moves(pos, board, en_passant_square),
+            Piece::King(_colour) => self.get_king_moves(pos, board, castlings),
         }
     }
 
@@ -510,7 +3204,7 @@ impl Piece {
     /// 
     /// * `pos`: The position of the piece that moves are gotten from. In usize tuple format.
     /// * `board`: The board. A 2d vector of Pieces.
-    fn get_rook_moves(&self, pos: (usize, usize), board: &Vec<Vec<Piece>>) -> Vec<(usize, usize)>{
+    fn get_rook_moves(&self, pos: (usize, usize), board: &[Vec<Piece>]) -> Vec<(usize, usize)>{
         let mut moves = Vec::new();
         for number in 1..8 {
             if pos.1 + number >= 8 { break; }
@@ -575,31 +3269,29 @@ impl Piece {
     /// 
     /// * `pos`: The position of the piece that moves are gotten from. In usize tuple format.
     /// * `board`: The board. A 2d vector of Pieces.
-    fn get_bishop_moves(&self, pos: (usize, usize), board: &Vec<Vec<Piece>>) -> Vec<(usize, usize)>{
+    fn get_bishop_moves(&self, pos: (usize, usize), board: &[Vec<Piece>]) -> Vec<(usize, usize)>{
         let mut moves = Vec::new();
-        macro_rules! bishop_move {
-            ($number1:tt, $axis1:tt, $number2:tt, $axis2:tt, $br1:tt, $br2:tt) => {
-                for number in 1..8 {
-                    if pos.0 + $number1 $axis1 number == $br1 || pos.1 + $number2 $axis2 number == $br2 { 
+        // Steps are signed and bounds-checked before indexing, so no usize arithmetic can underflow at the edges of the board.
+        for &(step1, step2) in [(1, 1), (1, -1), (-1, 1), (-1, -1)].iter() {
+            for number in 1..8 {
+                let x = pos.0 as i32 + step1 * number;
+                let y = pos.1 as i32 + step2 * number;
+                if !(0..8).contains(&x) || !(0..8).contains(&y) {
+                    break;
+                }
+                let (x, y) = (x as usize, y as usize);
+                if board[x][y] == Piece::Empty {
+                    moves.push((x, y));
+                } else {
+                    if board[x][y].get_colour().unwrap() == self.get_colour().unwrap() {
                         break;
-                    }
-                    if board[pos.0 $axis1 number][pos.1 $axis2 number] == Piece::Empty {
-                        moves.push((pos.0 $axis1 number, pos.1 $axis2 number));
                     } else {
-                        if board[pos.0 $axis1 number][pos.1 $axis2 number].get_colour().unwrap() == self.get_colour().unwrap() {
-                            break;
-                        } else {
-                            moves.push((pos.0 $axis1 number, pos.1 $axis2 number));
-                            break;
-                        }
+                        moves.push((x, y));
+                        break;
                     }
-                };
-            };
+                }
+            }
         }
-        bishop_move!(0, +, 0, +, 8, 8);
-        bishop_move!(0, +, 1, -, 8, 0);
-        bishop_move!(1, -, 0, +, 0, 8);
-        bishop_move!(1, -, 1, -, 0, 0);
         moves
     }
 
@@ -611,21 +3303,11 @@ impl Piece {
     /// 
     /// * `pos`: The position of the piece that moves are gotten from. In usize tuple format.
     /// * `board`: The board. A 2d vector of Pieces.
-    fn get_king_moves(&self, pos: (usize, usize), board: &Vec<Vec<Piece>>, castlings: (bool, bool, bool, bool)) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
-        for x in 0..3 {
-            if pos.0 + x == 0 || pos.0 + x == 9 { continue; }
-            for y in 0..3 {
-                if pos.1 + y == 0 || pos.1 + y == 9 { continue; }
-                if board[pos.0 + x - 1][pos.1 + y - 1] == Piece::Empty {
-                    moves.push((pos.0 + x - 1, pos.1 + y - 1));
-                } else {
-                    if board[pos.0 + x - 1][pos.1 + y - 1].get_colour().unwrap() != self.get_colour().unwrap() {
-                        moves.push((pos.0 + x - 1, pos.1 + y - 1));
-                    }
-                }
-            }
-        }
+    fn get_king_moves(&self, pos: (usize, usize), board: &[Vec<Piece>], castlings: (bool, bool, bool, bool)) -> Vec<(usize, usize)> {
+        let mut moves = king_table()[pos.0 * 8 + pos.1].iter()
+            .copied()
+            .filter(|to| board[to.0][to.1].get_colour() != self.get_colour())
+            .collect::<Vec<(usize, usize)>>();
         match self.get_colour().unwrap() {
             Colour::White => {
                 let threatened_squares = {
@@ -639,7 +3321,8 @@ impl Piece {
                     }
                     threat
                 };
-                if castlings.0 {
+                let can_castle = pos == convert_square("e1") && !threatened_squares.contains(&pos);
+                if castlings.0 && can_castle && board[7][7] == Piece::Rook(Colour::White) {
                     let sq1 = convert_square("f1");
                     let sq2 = convert_square("g1");
                     if board[sq1.0][sq1.1] == Piece::Empty 
@@ -649,7 +3332,7 @@ impl Piece {
                         moves.push(convert_square("g1"));
                     }
                 }
-                if castlings.1 {
+                if castlings.1 && can_castle && board[7][0] == Piece::Rook(Colour::White) {
                     let sq1 = convert_square("d1");
                     let sq2 = convert_square("c1");
                     let sq3 = convert_square("b1");
@@ -657,8 +3340,7 @@ impl Piece {
                         && board[sq2.0][sq2.1] == Piece::Empty 
                         && board[sq3.0][sq3.1] == Piece::Empty
                         && !threatened_squares.contains(&sq1)
-                        && !threatened_squares.contains(&sq2)
-                        && !threatened_squares.contains(&sq3) {
+                        && !threatened_squares.contains(&sq2) {
                         moves.push(convert_square("c1"));
                     }
                 }
@@ -675,7 +3357,8 @@ impl Piece {
                     }
                     threat
                 };
-                if castlings.2 {
+                let can_castle = pos == convert_square("e8") && !threatened_squares.contains(&pos);
+                if castlings.2 && can_castle && board[0][7] == Piece::Rook(Colour::Black) {
                     let sq1 = convert_square("f8");
                     let sq2 = convert_square("g8");
                     if board[sq1.0][sq1.1] == Piece::Empty 
@@ -685,7 +3368,7 @@ impl Piece {
                         moves.push(convert_square("g8"));
                     }
                 }
-                if castlings.3 {
+                if castlings.3 && can_castle && board[0][0] == Piece::Rook(Colour::Black) {
                     let sq1 = convert_square("d8");
                     let sq2 = convert_square("c8");
                     let sq3 = convert_square("b8");
@@ -693,13 +3376,21 @@ impl Piece {
                         && board[sq2.0][sq2.1] == Piece::Empty 
                         && board[sq3.0][sq3.1] == Piece::Empty
                         && !threatened_squares.contains(&sq1)
-                        && !threatened_squares.contains(&sq2)
-                        && !threatened_squares.contains(&sq3) {
+                        && !threatened_squares.contains(&sq2) {
                         moves.push(convert_square("c8"));
                     }
                 }
             }
         }
+        let mut enemy_kings = Vec::new();
+        for (x, rank) in board.iter().enumerate() {
+            for (y, piece) in rank.iter().enumerate() {
+                if let Piece::King(colour) = piece {
+                    if colour != self.get_colour().unwrap() { enemy_kings.push((x, y)); }
+                }
+            }
+        }
+        moves.retain(|mov| enemy_kings.iter().all(|king| king.0.abs_diff(mov.0) > 1 || king.1.abs_diff(mov.1) > 1));
         moves
     }
 
@@ -711,30 +3402,11 @@ impl Piece {
     /// 
     /// * `pos`: The position of the piece that moves are gotten from. In usize tuple format.
     /// * `board`: The board. A 2d vector of Pieces.
-    fn get_knight_moves(&self, pos: (usize, usize), board: &Vec<Vec<Piece>>) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
-        macro_rules! knight_move {
-            ($number1:tt, $axis1:tt, $comp1:tt, $number2:tt, $axis2:tt, $comp2:tt) => {
-                if (pos.0 as f32) $comp1 3.5 $axis1 3.5 $axis1 -(($number1 - 1) as f32) && (pos.1 as f32) $comp2 3.5 $axis2 3.5 $axis2 -(($number2 - 1) as f32) {
-                    if board[pos.0 $axis1 $number1][pos.1 $axis2 $number2] == Piece::Empty {
-                        moves.push((pos.0 $axis1 $number1, pos.1 $axis2 $number2));
-                    } else {
-                        if board[pos.0 $axis1 $number1][pos.1 $axis2 $number2].get_colour().unwrap() != self.get_colour().unwrap() {
-                            moves.push((pos.0 $axis1 $number1, pos.1 $axis2 $number2));
-                        }
-                    }
-                }
-            };
-        }
-        knight_move!(1, -, >, 2, -, >);
-        knight_move!(1, -, >, 2, +, <);
-        knight_move!(1, +, <, 2, -, >);
-        knight_move!(1, +, <, 2, +, <);
-        knight_move!(2, -, >, 1, -, >);
-        knight_move!(2, -, >, 1, +, <);
-        knight_move!(2, +, <, 1, -, >);
-        knight_move!(2, +, <, 1, +, <);
-        moves
+    fn get_knight_moves(&self, pos: (usize, usize), board: &[Vec<Piece>]) -> Vec<(usize, usize)> {
+        knight_table()[pos.0 * 8 + pos.1].iter()
+            .copied()
+            .filter(|to| board[to.0][to.1].get_colour() != self.get_colour())
+            .collect()
     }
 
     /// Internal helper function which shouldn't be used outside of Piece implementation.
@@ -746,7 +3418,7 @@ impl Piece {
     /// * `pos`: The position of the piece that moves are gotten from. In usize tuple format.
     /// * `board`: The board. A 2d vector of Pieces.
     /// * `en_passant_square`: The current square that can be captured through en_passant_square. Any non-existent square is accepted en-passant being impossible.
-    fn get_pawn_moves(&self, pos: (usize, usize), board: &Vec<Vec<Piece>>, en_passant_square: (usize, usize)) -> Vec<(usize, usize)> {
+    fn get_pawn_moves(&self, pos: (usize, usize), board: &[Vec<Piece>], en_passant_square: (usize, usize)) -> Vec<(usize, usize)> {
         match self.get_colour().unwrap() {
             Colour::Black => {
                 let mut moves = Vec::new();
@@ -770,7 +3442,7 @@ impl Piece {
             },
             Colour::White => {
                 let mut moves = Vec::new();
-                if pos.0 > 1 {
+                if pos.0 > 0 {
                     if pos.1 < 7 && ((board[pos.0 - 1][pos.1 + 1] != Piece::Empty && board[pos.0 - 1][pos.1 + 1].get_colour().unwrap() != self.get_colour().unwrap())
                         || (en_passant_square == (pos.0 - 1, pos.1 + 1))) {
                         moves.push((pos.0 - 1, pos.1 + 1));
@@ -791,6 +3463,30 @@ impl Piece {
         }
     }
 
+    /// Helper function returning the material value of a piece, counted in pawns. Kings and empty squares are worth nothing.
+    fn value(&self) -> i32 {
+        match self {
+            Piece::Queen(_) => 9,
+            Piece::Rook(_) => 5,
+            Piece::Bishop(_) | Piece::Knight(_) => 3,
+            Piece::Pawn(_) => 1,
+            Piece::King(_) | Piece::Empty => 0,
+        }
+    }
+
+    /// Helper function to retrieve the kind of a piece, without its colour. Returns None for an empty piece.
+    fn kind(&self) -> Option<PieceKind> {
+        match self {
+            Piece::King(_) => Some(PieceKind::King),
+            Piece::Queen(_) => Some(PieceKind::Queen),
+            Piece::Rook(_) => Some(PieceKind::Rook),
+            Piece::Bishop(_) => Some(PieceKind::Bishop),
+            Piece::Knight(_) => Some(PieceKind::Knight),
+            Piece::Pawn(_) => Some(PieceKind::Pawn),
+            Piece::Empty => None,
+        }
+    }
+
     /// Helper function to retrieve the colour out of a piece.
     /// Returns the relevant colour for any piece, and returns None for an empty piece.
     fn get_colour(&self) -> Option<&Colour> {
@@ -801,49 +3497,364 @@ impl Piece {
     }
 }
 
+/// Enumerable naming a kind of piece, without the colour it belongs to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    King,
+    Queen,
+    Rook,
+    Bishop,
+    Knight,
+    Pawn,
+}
+
 /// Colour enumerable used to identify the colour that any given piece belongs to.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Colour {
     White,
     Black
 }
 
+impl Colour {
+    /// Returns the other colour.
+    pub fn opposite(self) -> Colour {
+        match self {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        }
+    }
+
+    /// Returns true for white.
+    pub fn is_white(self) -> bool {
+        self == Colour::White
+    }
+}
+
 /// Goes through all the moves given in moves, and removes any that would place the player in check.
+/// A pawn moving diagonally onto an empty square captures en passant, so the pawn it passes is removed as well,
+/// which matters when both pawns stood between the king and an enemy rook or queen on the same rank.
 /// 
 /// # Arguments
 /// 
 /// `pos`: The position of the piece which is being moved.
 /// `board`: The board of the game.
 /// `moves`: The moves to be cleaned.
-fn clean_moves(pos: (usize, usize), board: &Vec<Vec<Piece>>, moves: Vec<(usize, usize)>, turn: Colour) -> Vec<(usize, usize)> {
-    let mut bad_moves = Vec::new();
+fn clean_moves(pos: (usize, usize), board: &[Vec<Piece>], moves: Vec<(usize, usize)>, turn: Colour) -> Vec<(usize, usize)> {
     let mut clean_moves = Vec::new();
-    for mov_idx in 0..moves.len() {
-        let mut theoretical_game = Game::new();
+    for mov in moves {
+        let mut theoretical_game = Game::new_empty();
         theoretical_game.current_turn = turn;
-        theoretical_game.board = board.clone();
-        theoretical_game.board[moves[mov_idx].0][moves[mov_idx].1] = board[pos.0][pos.1].clone();
+        theoretical_game.board = board.to_vec();
+        theoretical_game.board[mov.0][mov.1] = board[pos.0][pos.1];
         theoretical_game.board[pos.0][pos.1] = Piece::Empty;
-        if theoretical_game.get_game_state(false) == GameState::Check {
-            bad_moves.push(mov_idx);
+        if matches!(board[pos.0][pos.1], Piece::Pawn(_)) && mov.1 != pos.1 && board[mov.0][mov.1] == Piece::Empty {
+            theoretical_game.board[pos.0][mov.1] = Piece::Empty;
         }
-    }
-    for number in 0..moves.len() {
-        if !bad_moves.contains(&number) {
-            clean_moves.push(moves[number]);
+        if !matches!(theoretical_game.get_game_state(false), GameState::Check(_)) {
+            clean_moves.push(mov);
         }
     }
     clean_moves
 }
 
+/// Internal helper function returning the squares a knight can reach from each square of an empty board, indexed by rank * 8 + file.
+/// The table is built on first use.
+fn knight_table() -> &'static [Vec<(usize, usize)>] {
+    static TABLE: OnceLock<Vec<Vec<(usize, usize)>>> = OnceLock::new();
+    TABLE.get_or_init(|| step_table(&[(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)]))
+}
+
+/// Internal helper function returning the squares a king can reach from each square of an empty board, not counting castling,
+/// indexed by rank * 8 + file. The table is built on first use.
+fn king_table() -> &'static [Vec<(usize, usize)>] {
+    static TABLE: OnceLock<Vec<Vec<(usize, usize)>>> = OnceLock::new();
+    TABLE.get_or_init(|| step_table(&[(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)]))
+}
+
+/// Internal helper function returning, for each square of the board, the squares one of the given steps away that are still on the board.
+fn step_table(steps: &[(i32, i32)]) -> Vec<Vec<(usize, usize)>> {
+    (0..64).map(|idx| {
+        let (x, y) = (idx / 8, idx % 8);
+        steps.iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|(x, y)| (0..8).contains(x) && (0..8).contains(y))
+            .map(|(x, y)| (x as usize, y as usize))
+            .collect()
+    }).collect()
+}
+
+/// Returns the squares the piece on the given square can legally move to in the position described by a FEN-notated string,
+/// or what was wrong with the string if it couldn't be read. A piece of the side not to move gets the moves it would have if it were,
+/// as given by `Game::legal_moves_grouped`, and an empty square, or a string that isn't a square as checked by `Game::is_valid_square`, gives an empty list.
+///
+/// # Arguments
+///
+/// `fen`: The position, as a FEN-notated string.
+/// `square`: The square of the piece, in chess notation.
+pub fn legal_moves_in_fen(fen: &str, square: &str) -> Result<Vec<String>, FenError> {
+    let game = Game::from_fen(fen)?;
+    if !Game::is_valid_square(square) {
+        return Ok(Vec::new());
+    }
+    let pos = convert_square(square);
+    let colour_is_white = match game.board[pos.0][pos.1].get_colour() {
+        Some(colour) => colour.is_white(),
+        None => return Ok(Vec::new()),
+    };
+    Ok(game.legal_moves_grouped(colour_is_white)
+        .into_iter()
+        .find(|(from, _)| from == square)
+        .map_or_else(Vec::new, |(_, moves)| moves))
+}
+
+/// Returns the squares strictly between two squares, if they share a rank, file or diagonal, and None otherwise,
+/// including when either string isn't a square.
+/// Adjacent squares have no squares between them, giving an empty list.
+///
+/// # Arguments
+///
+/// `a`: The first square, in chess notation.
+/// `b`: The second square, in chess notation.
+pub fn squares_between(a: &str, b: &str) -> Option<Vec<String>> {
+    if !Game::is_valid_square(a) || !Game::is_valid_square(b) {
+        return None;
+    }
+    let from = convert_square(a);
+    let to = convert_square(b);
+    let dx = to.0 as i32 - from.0 as i32;
+    let dy = to.1 as i32 - from.1 as i32;
+    if (dx == 0 && dy == 0) || (dx != 0 && dy != 0 && dx.abs() != dy.abs()) {
+        return None;
+    }
+    let steps = dx.abs().max(dy.abs());
+    let mut squares = Vec::new();
+    for step in 1..steps {
+        let x = from.0 as i32 + dx.signum() * step;
+        let y = from.1 as i32 + dy.signum() * step;
+        squares.push(square_name((x as usize, y as usize)));
+    }
+    Some(squares)
+}
+
+/// Reads every game in a collection of games in PGN, such as a .pgn database file, parsing each with `Game::from_pgn`.
+/// A game ends at the first blank line or tag pair following its movetext, and a game that can't be read
+/// gives an error in its place without stopping the games after it from being read.
+///
+/// # Arguments
+///
+/// * `text` - string holding the games, one after the other.
+pub fn parse_pgn_collection(text: &str) -> Vec<Result<Game, PgnError>> {
+    let mut games = Vec::new();
+    let mut pgn = String::new();
+    let mut in_movetext = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if in_movetext && (line.is_empty() || line.starts_with('[')) {
+            games.push(Game::from_pgn(&pgn));
+            pgn.clear();
+            in_movetext = false;
+        }
+        if !line.is_empty() && !line.starts_with('[') {
+            in_movetext = true;
+        }
+        pgn.push_str(line);
+        pgn.push('\n');
+    }
+    if in_movetext {
+        games.push(Game::from_pgn(&pgn));
+    }
+    games
+}
+
+/// Returns whether any position in a list of FEN-notated strings occurs three or more times.
+/// Positions are compared by their first four fields, the piece placement, side to move, castling rights and en-passant square,
+/// so that the move clocks don't keep repeated positions apart.
+///
+/// # Arguments
+///
+/// * `history` - the FEN-notated strings of the positions of a game.
+pub fn is_threefold(history: &[&str]) -> bool {
+    let mut counts = HashMap::new();
+    for fen in history {
+        let key = fen.split_whitespace().take(4).collect::<Vec<&str>>();
+        let count = counts.entry(key).or_insert(0);
+        *count += 1;
+        if *count >= 3 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Searches the current position by iterative deepening and returns the best move found before `max_millis` milliseconds have passed,
+/// or None if the game is over. Each depth is searched in full with alpha-beta pruning on top of `Game::evaluate`,
+/// and the time is checked between nodes; a depth left unfinished when the time runs out is thrown away,
+/// so the move returned is always the best of the deepest finished search. At least one legal move is returned
+/// even with no time at all, the first of `Game::ordered_legal_moves`.
+///
+/// # Arguments
+///
+/// * `game` - the game to search the current position of.
+/// * `max_millis` - the time budget of the search, in milliseconds.
+pub fn search_best_move_timed(game: &Game, max_millis: u64) -> Option<Move> {
+    use std::time::{Duration, Instant};
+
+    let deadline = Instant::now() + Duration::from_millis(max_millis);
+    let mut moves = game.ordered_legal_moves();
+    let mut best = *moves.first()?;
+    for depth in 1.. {
+        let mut alpha = -MATE_SCORE - 1;
+        let mut best_at_depth = None;
+        for &mov in moves.iter() {
+            let mut child = game.clone_position();
+            if child.play_move(mov).is_err() {
+                continue;
+            }
+            let score = match negamax(&child, depth - 1, 1, -MATE_SCORE - 1, -alpha, Some(deadline)) {
+                Some(score) => -score,
+                None => return Some(best),
+            };
+            if score > alpha {
+                alpha = score;
+                best_at_depth = Some(mov);
+            }
+        }
+        if let Some(mov) = best_at_depth {
+            best = mov;
+        }
+        if alpha >= MATE_SCORE - depth as i32 || Instant::now() >= deadline {
+            break;
+        }
+        // Searching the best move first at the next depth lets alpha-beta prune more of the others.
+        moves.retain(|mov| *mov != best);
+        moves.insert(0, best);
+    }
+    Some(best)
+}
+
+/// Internal helper function scoring a position for the player whose turn it is by alpha-beta search to the given depth,
+/// or returning None once the deadline, if any, has passed. `ply` counts the moves made since the root, so that sooner mates score higher.
+fn negamax(game: &Game, depth: usize, ply: i32, mut alpha: i32, beta: i32, deadline: Option<std::time::Instant>) -> Option<i32> {
+    if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+        return None;
+    }
+    match game.game_state() {
+        GameState::Checkmate => return Some(-MATE_SCORE + ply),
+        GameState::Stalemate | GameState::Draw(_) => return Some(0),
+        _ => (),
+    }
+    if depth == 0 {
+        let score = game.evaluate();
+        return Some(if game.current_turn == Colour::White { score } else { -score });
+    }
+
+    for mov in game.ordered_legal_moves() {
+        let mut child = game.clone_position();
+        if child.play_move(mov).is_err() {
+            continue;
+        }
+        let score = -negamax(&child, depth - 1, ply + 1, -beta, -alpha, deadline)?;
+        if score >= beta {
+            return Some(beta);
+        }
+        alpha = alpha.max(score);
+    }
+    Some(alpha)
+}
+
+/// Internal helper function removing the comments and variations from PGN movetext, leaving the moves of the mainline.
+/// Comments run from { to } or from ; to the end of the line, and variations, which may be nested, are enclosed in ( and ).
+fn pgn_mainline(movetext: &str) -> String {
+    let mut mainline = String::new();
+    let mut depth = 0;
+    let mut comment_end = None;
+    for _char in movetext.chars() {
+        if let Some(end) = comment_end {
+            if _char == end {
+                comment_end = None;
+                mainline.push(' ');
+            }
+            continue;
+        }
+        match _char {
+            '{' => comment_end = Some('}'),
+            ';' => comment_end = Some('\n'),
+            '(' => depth += 1,
+            ')' => {
+                if depth > 0 { depth -= 1; }
+                mainline.push(' ');
+            },
+            _ if depth > 0 => (),
+            _ => mainline.push(_char),
+        }
+    }
+    mainline
+}
+
+/// Reads one of the move counters of a FEN-notated string, which must consist of digits only.
+/// Numbers too large to be stored are read as the largest number that can be, rather than rejected.
+fn parse_fen_number(field: &str) -> Option<usize> {
+    if field.is_empty() || !field.chars().all(|_char| _char.is_ascii_digit()) {
+        return None;
+    }
+    Some(field.parse::<usize>().unwrap_or(usize::MAX))
+}
+
+/// Takes a FEN piece letter, such as K for a White king or p for a Black pawn, and converts it into the piece.
+/// Returns None for any other character.
+fn piece_from_char(_char: char) -> Option<Piece> {
+    match _char {
+        'K' => Some(Piece::King(Colour::White)),
+        'k' => Some(Piece::King(Colour::Black)),
+        'Q' => Some(Piece::Queen(Colour::White)),
+        'q' => Some(Piece::Queen(Colour::Black)),
+        'R' => Some(Piece::Rook(Colour::White)),
+        'r' => Some(Piece::Rook(Colour::Black)),
+        'B' => Some(Piece::Bishop(Colour::White)),
+        'b' => Some(Piece::Bishop(Colour::Black)),
+        'N' => Some(Piece::Knight(Colour::White)),
+        'n' => Some(Piece::Knight(Colour::Black)),
+        'P' => Some(Piece::Pawn(Colour::White)),
+        'p' => Some(Piece::Pawn(Colour::Black)),
+        _ => None,
+    }
+}
+
+/// Takes a piece and converts it into its FEN piece letter, such as K for a White king or p for a Black pawn.
+/// The inverse of piece_from_char, returning None for an empty square.
+fn piece_char(piece: Piece) -> Option<char> {
+    let _char = match piece.kind()? {
+        PieceKind::King => 'k',
+        PieceKind::Queen => 'q',
+        PieceKind::Rook => 'r',
+        PieceKind::Bishop => 'b',
+        PieceKind::Knight => 'n',
+        PieceKind::Pawn => 'p',
+    };
+    if piece.get_colour() == Some(&Colour::White) { Some(_char.to_ascii_uppercase()) } else { Some(_char) }
+}
+
+/// Takes a tuple of x and y on the game board and converts it into a string such as a4 or c6. The inverse of convert_square.
+///
+/// # Arguments
+///
+/// `pos`: The position on the board, in usize tuple format.
+fn square_name(pos: (usize, usize)) -> String {
+    let mut name = String::new();
+    name.push((b'a' + pos.1 as u8) as char);
+    name.push_str(&(8 - pos.0).to_string());
+    name
+}
+
 /// Takes a string such as a4 or c6 and converts it into a tuple of x and y friendly to the game board.
+/// Panics for anything that isn't a square, so input from outside should be checked with `Game::is_valid_square` first.
 /// 
 /// # Arguments
 /// 
 /// `square`: A string literal with a square in chess notation.
 fn convert_square(square: &str) -> (usize, usize) {
     let column = {
-        match square.chars().nth(0).unwrap() {
+        match square.chars().next().unwrap() {
             'a' => 0,
             'b' => 1,
             'c' => 2,
@@ -0,0 +1,74 @@
+use murnion_chess::{Game, GameState};
+use text_io::try_read;
+
+/// Interactive two-player game in the terminal.
+/// Moves are entered as "<square> <square>", such as "e2 e4", and "fen <string>" loads an arbitrary position.
+/// A FEN given on the command line is used as the starting position, falling back to the standard one if it can't be read.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let mut game = if args.is_empty() {
+        Game::new()
+    } else {
+        match Game::from_fen(&args.join(" ")) {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                println!("Could not read FEN: {:?}", error);
+                Game::new()
+            }
+        }
+    };
+    print!("{}", board_into_display(&game.board_fen()));
+
+    loop {
+        let input: Result<String, _> = try_read!("{}\n");
+        let input = match input {
+            Ok(line) => line.trim().to_string(),
+            Err(_) => break,
+        };
+
+        if input == "quit" {
+            break;
+        } else if let Some(fen) = input.strip_prefix("fen ") {
+            match Game::from_fen(fen.trim()) {
+                Ok(loaded) => game = loaded,
+                Err(error) => {
+                    println!("Could not read FEN: {:?}", error);
+                    continue;
+                }
+            }
+        } else if let Err(error) = game.try_take_turn(&input) {
+            println!("Illegal move: {:?}", error);
+            continue;
+        }
+
+        print!("{}", board_into_display(&game.board_fen()));
+        println!("{:?}", game.game_state());
+        if matches!(game.game_state(), GameState::Checkmate | GameState::Stalemate | GameState::Draw(_)) {
+            break;
+        }
+    }
+}
+
+/// Turns the piece placement field of a FEN-notated string into a printable board, with rank 8 at the top.
+///
+/// # Arguments
+///
+/// * `placement` - the piece placement field of a FEN-notated string, as given by `Game::board_fen`.
+fn board_into_display(placement: &str) -> String {
+    let mut display = String::new();
+    for (idx, rank) in placement.split('/').enumerate() {
+        display.push_str(&(8 - idx).to_string());
+        for _char in rank.chars() {
+            match _char.to_digit(10) {
+                Some(empties) => for _ in 0..empties { display.push_str(" ."); },
+                None => {
+                    display.push(' ');
+                    display.push(_char);
+                },
+            }
+        }
+        display.push('\n');
+    }
+    display.push_str("  a b c d e f g h\n");
+    display
+}
@@ -109,6 +109,7 @@ mod game_tests {
         test_game.en_passant_square = (5, 4);
         test_game.castlings = (false, false, true, true);
         test_game.halfmove_clock = 20;
+        test_game.start_fen = Some("rnbqkbnr/pp1ppppp/2p5/8/4P3/8/PPPP1PPP/RNBQKBNR b kq e3 20 2".to_string());
 
         assert_eq!(fen_game, test_game);
         assert_eq!(fen_game.current_turn, test_game.current_turn);
@@ -118,7 +119,7 @@ mod game_tests {
     fn get_fen_works_correctly() {
         use crate::Game;
 
-        let mut game = Game::new();
+        let game = Game::new();
         let fen = game.get_fen();
         let expected_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
@@ -270,10 +271,2318 @@ mod game_tests {
         legal_moves: [f8, d7, e7, f7],
     }
 
+    test!{
+        name: king_cannot_castle_out_of_check,
+        fen: "4k3/8/8/8/4r3/8/8/R3K2R w KQ - 0 0",
+        piece: e1,
+        legal_moves: [d1, d2, f1, f2],
+    }
+
+    test!{
+        name: king_cannot_castle_without_rook_on_home_square,
+        fen: "4k3/8/8/8/8/8/8/4K3 w KQ - 0 0",
+        piece: e1,
+        legal_moves: [d1, d2, e2, f1, f2],
+    }
+
+    test!{
+        name: king_castles_queenside_past_attacked_b_file,
+        fen: "1r2k3/8/8/8/8/8/8/R3K3 w Q - 0 0",
+        piece: e1,
+        legal_moves: [c1, d1, d2, e2, f1, f2],
+    }
+
     test!{
         name: black_king_checks_correctly,
         fen: "k6r/1P6/8/8/8/8/8/8 b KQkq - 0 0",
         piece: h8,
         legal_moves: [],
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn pawn_structure_counts_doubled_pawns() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("8/8/8/8/8/2P5/2P5/8 w  - 0 0");
+        let structure = game.pawn_structure(true);
+
+        assert_eq!(structure.doubled, 1);
+        assert_eq!(structure.isolated, 2);
+    }
+
+    #[test]
+    fn pawn_structure_counts_isolated_pawns() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("8/pp6/8/8/8/8/P1PP4/8 w  - 0 0");
+        let structure = game.pawn_structure(true);
+
+        assert_eq!(structure.doubled, 0);
+        assert_eq!(structure.isolated, 1);
+        assert_eq!(structure.passed, 1);
+    }
+
+    #[test]
+    fn clone_position_drops_history() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.take_turn("e2 e4".to_string());
+        game.take_turn("e7 e5".to_string());
+        let clone = game.clone_position();
+
+        assert_eq!(clone.get_fen(), game.get_fen());
+        assert_eq!(game.history.len(), 2);
+        assert!(clone.history.is_empty());
+    }
+
+    #[test]
+    fn undo_move_restores_position() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("r3k3/8/8/3pP3/8/8/8/4K2R w Kq d6 0 10");
+        let fen = game.get_fen();
+        game.take_turn("e5 d6".to_string());
+        game.undo_move();
+        assert_eq!(game.get_fen(), fen);
+
+        game.take_turn("e1 g1".to_string());
+        game.undo_move();
+        assert_eq!(game.get_fen(), fen);
+        assert_eq!(game.undo_move(), None);
+    }
+
+    #[test]
+    fn validate_rejects_capturable_king() {
+        use crate::Game;
+        use crate::PositionError;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/8/8/8/8/8/8/4RK2 w  - 0 1");
+        assert_eq!(game.validate(), Err(PositionError::IllegalCheck));
+
+        game.set_state_from_fen("4k3/8/8/8/8/8/8/4RK2 b  - 0 1");
+        assert_eq!(game.validate(), Ok(()));
+    }
+
+    #[test]
+    fn default_promotion_is_kept_per_colour() {
+        use crate::Game;
+        use crate::Piece;
+        use crate::PieceKind;
+        use crate::Colour;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("8/P7/8/8/8/8/7p/8 w  - 0 1");
+        game.set_default_promotion(true, PieceKind::Knight);
+        game.take_turn("a7 a8".to_string());
+        game.take_turn("h2 h1".to_string());
+
+        assert_eq!(game.board[0][0], Piece::Knight(Colour::White));
+        assert_eq!(game.board[7][7], Piece::Queen(Colour::Black));
+    }
+
+    #[test]
+    fn previous_fen_returns_position_before_last_move() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert_eq!(game.previous_fen(), None);
+
+        let start = game.get_fen();
+        game.take_turn("g1 f3".to_string());
+
+        assert_eq!(game.previous_fen(), Some(start));
+        assert_eq!(game.history.len(), 1);
+    }
+
+    #[test]
+    fn move_time_decrements_mover_clock() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert_eq!(game.time_remaining(true), None);
+
+        game.set_time_control(60_000, 60_000, 0);
+        game.take_turn("e2 e4".to_string());
+        game.record_move_time(5_000);
+
+        assert_eq!(game.time_remaining(true), Some(55_000));
+        assert_eq!(game.time_remaining(false), Some(60_000));
+    }
+
+    #[test]
+    fn move_time_adds_increment() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.set_time_control(60_000, 60_000, 2_000);
+        game.take_turn("e2 e4".to_string());
+        game.record_move_time(5_000);
+        game.take_turn("e7 e5".to_string());
+        game.record_move_time(1_000);
+
+        assert_eq!(game.time_remaining(true), Some(57_000));
+        assert_eq!(game.time_remaining(false), Some(61_000));
+    }
+
+    #[test]
+    fn move_time_causes_timeout() {
+        use crate::Game;
+        use crate::GameState;
+        use crate::Colour;
+
+        let mut game = Game::new();
+        game.set_time_control(60_000, 3_000, 2_000);
+        game.take_turn("e2 e4".to_string());
+        game.record_move_time(1_000);
+        game.take_turn("e7 e5".to_string());
+        let state = game.record_move_time(3_000);
+
+        assert_eq!(state, Some(GameState::Timeout(Colour::White)));
+        assert_eq!(game.game_state(), GameState::Timeout(Colour::White));
+        assert_eq!(game.time_remaining(false), Some(0));
+    }
+
+    #[test]
+    fn is_defended_finds_defending_pawn() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("8/8/8/3N4/2P5/8/5B2/8 w  - 0 1");
+
+        assert!(game.is_defended("d5"));
+        assert!(!game.is_defended("f2"));
+        assert_eq!(game.attackers_of("d5", true), vec!["c4".to_string()]);
+    }
+
+    #[test]
+    fn hanging_pieces_reports_attacked_undefended_rook() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/8/8/1b6/8/3R4/3P4/4K3 w  - 0 1");
+
+        assert_eq!(game.hanging_pieces(true), vec!["d3".to_string()]);
+        assert!(game.hanging_pieces(false).is_empty());
+    }
+
+    #[test]
+    fn transposed_position_key_is_found_in_hash_set() {
+        use crate::Game;
+        use std::collections::HashSet;
+
+        let mut seen = HashSet::new();
+        let mut game = Game::new();
+        game.take_turn("g1 f3".to_string());
+        game.take_turn("g8 f6".to_string());
+        game.take_turn("b1 c3".to_string());
+        seen.insert(game.position_key());
+
+        let mut transposed = Game::new();
+        transposed.take_turn("b1 c3".to_string());
+        transposed.take_turn("g8 f6".to_string());
+        transposed.take_turn("g1 f3".to_string());
+
+        assert!(seen.contains(&transposed.position_key()));
+    }
+
+    #[test]
+    fn from_fen_rejects_invalid_strings() {
+        use crate::Game;
+        use crate::FenError;
+
+        assert_eq!(Game::from_fen("not a fen"), Err(FenError::FieldCount));
+        assert_eq!(Game::from_fen("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Err(FenError::Piece('x')));
+        assert_eq!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"), Err(FenError::ActiveColour));
+        assert_eq!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Ok(Game::new()));
+    }
+
+    #[test]
+    fn try_set_state_from_fen_leaves_game_unchanged_on_error() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        let result = game.try_set_state_from_fen("8/8/8/8/8/8/8/8 w - z9 0 1");
+
+        assert!(result.is_err());
+        assert_eq!(game, Game::new());
+    }
+
+    #[test]
+    fn plies_since_capture_resets_on_capture() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.take_turn("g1 f3".to_string());
+        game.take_turn("b8 c6".to_string());
+        game.take_turn("f3 g5".to_string());
+        assert_eq!(game.plies_since_capture(), 3);
+        assert_eq!(game.total_plies(), 3);
+
+        game.take_turn("c6 d4".to_string());
+        game.take_turn("g5 f7".to_string());
+        assert_eq!(game.plies_since_capture(), 0);
+        assert_eq!(game.total_plies(), 5);
+
+        game.take_turn("d4 e6".to_string());
+        assert_eq!(game.plies_since_capture(), 1);
+
+        game.take_turn("e2 e4".to_string());
+        assert_eq!(game.plies_since_capture(), 0);
+        assert_eq!(game.total_plies(), 7);
+    }
+
+    #[test]
+    fn promotion_choices_for_promoting_move() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/P7/8/8/8/8/4P3/4K3 w  - 0 1");
+
+        assert_eq!(game.promotion_choices("a7", "a8"), Some(vec!['q', 'r', 'b', 'n']));
+    }
+
+    #[test]
+    fn promotion_choices_for_non_promoting_move() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/P7/8/8/8/8/4P3/4K3 w  - 0 1");
+
+        assert_eq!(game.promotion_choices("e2", "e4"), None);
+        assert_eq!(game.promotion_choices("a7", "b8"), None);
+    }
+
+    #[test]
+    fn resignation_gives_result() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert_eq!(game.result(), "*");
+        game.resign(true);
+        assert_eq!(game.result(), "0-1");
+
+        let mut game = Game::new();
+        game.take_turn("e2 e4".to_string());
+        game.resign(false);
+        assert_eq!(game.result(), "1-0");
+    }
+
+    #[test]
+    fn checkmate_and_timeout_give_result() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.take_turn("f2 f3".to_string());
+        game.take_turn("e7 e5".to_string());
+        game.take_turn("g2 g4".to_string());
+        game.take_turn("d8 h4".to_string());
+        assert_eq!(game.result(), "0-1");
+
+        let mut game = Game::new();
+        game.set_time_control(1_000, 1_000, 0);
+        game.take_turn("e2 e4".to_string());
+        game.record_move_time(2_000);
+        assert_eq!(game.result(), "0-1");
+    }
+
+    #[test]
+    fn apply_moves_rolls_back_on_illegal_move() {
+        use crate::Game;
+        use crate::MoveError;
+
+        let mut game = Game::new();
+        let result = game.apply_moves(&["e2 e4", "e7 e5", "e4 e5", "g1 f3"]);
+
+        assert_eq!(result, Err((2, MoveError::IllegalMove)));
+        assert_eq!(game, Game::new());
+
+        assert_eq!(game.apply_moves(&["e2 e4", "e7 e5"]), Ok(()));
+        assert_eq!(game.history.len(), 2);
+    }
+
+    #[test]
+    fn take_turn_rejects_illegal_moves() {
+        use crate::Game;
+        use crate::MoveError;
+
+        let mut game = Game::new();
+
+        assert_eq!(game.take_turn("e2 e5".to_string()), None);
+        assert_eq!(game.try_take_turn("e3 e4"), Err(MoveError::NoPiece));
+        assert_eq!(game.try_take_turn("e7 e5"), Err(MoveError::WrongColour));
+        assert_eq!(game, Game::new());
+    }
+
+    #[test]
+    fn is_promotion_only_for_pawns_reaching_last_rank() {
+        use crate::Game;
+        use crate::convert_square;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/P6R/8/8/8/8/p7/4K3 w  - 0 1");
+
+        assert!(game.is_promotion(convert_square("a7"), convert_square("a8")));
+        assert!(!game.is_promotion(convert_square("h7"), convert_square("h8")));
+        assert!(game.is_promotion(convert_square("a2"), convert_square("a1")));
+    }
+
+    #[test]
+    fn last_move_squares_returns_latest_move() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert_eq!(game.last_move_squares(), None);
+
+        game.take_turn("e2 e4".to_string());
+        game.take_turn("g8 f6".to_string());
+
+        assert_eq!(game.last_move_squares(), Some(("g8".to_string(), "f6".to_string())));
+    }
+
+    test!{
+        name: white_king_keeps_away_from_king,
+        fen: "8/8/3k4/8/3K4/8/8/8 w  - 0 0",
+        piece: d4,
+        legal_moves: [c3, d3, e3, c4, e4],
+    }
+
+    test!{
+        name: black_king_keeps_away_from_king,
+        fen: "8/8/3k4/8/3K4/8/8/8 b  - 0 0",
+        piece: d6,
+        legal_moves: [c7, d7, e7, c6, e6],
+    }
+
+    #[test]
+    fn move_log_iccf_writes_numeric_moves() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.apply_moves(&["e2 e4", "e7 e5", "g1 f3"]).unwrap();
+        assert_eq!(game.move_log_iccf(), "5254 5755 7163");
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/1P6/8/8/8/8/8/4K3 w  - 0 1");
+        game.select_promotion('n');
+        game.take_turn("b7 b8".to_string());
+        assert_eq!(game.move_log_iccf(), "27284");
+    }
+
+    #[test]
+    fn mirror_twice_is_identity() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("r3k2r/ppp2ppp/2n5/3pP3/8/5N2/PPP2PPP/R1B1K2R w Kq d6 0 9");
+        let mirrored = game.mirror();
+
+        assert_eq!(mirrored.get_fen(), "r1b1k2r/ppp2ppp/5n2/8/3Pp3/2N5/PPP2PPP/R3K2R b Qk d3 0 9");
+        assert_eq!(mirrored.mirror(), game);
+        assert_eq!(game.material_balance(), 3);
+        assert_eq!(mirrored.material_balance(), -3);
+    }
+
+    #[test]
+    fn from_board_array_builds_start_position() {
+        use crate::Game;
+
+        let mut board = [[None; 8]; 8];
+        let back_rank = ['r', 'n', 'b', 'q', 'k', 'b', 'n', 'r'];
+        for file in 0..8 {
+            board[0][file] = Some(back_rank[file]);
+            board[1][file] = Some('p');
+            board[6][file] = Some('P');
+            board[7][file] = Some(back_rank[file].to_ascii_uppercase());
+        }
+        let game = Game::from_board_array(board, true).unwrap();
+
+        assert_eq!(game.get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1");
+        assert_eq!(game.validate(), Ok(()));
+    }
+
+    #[test]
+    fn check_names_checked_colour() {
+        use crate::Game;
+        use crate::GameState;
+        use crate::Colour;
+
+        let mut game = Game::new();
+        game.apply_moves(&["e2 e4", "f7 f6", "d1 h5"]).unwrap();
+        assert_eq!(game.game_state(), GameState::Check(Colour::Black));
+
+        let mut game = Game::new();
+        game.apply_moves(&["f2 f3", "e7 e5", "e2 e4"]).unwrap();
+        assert_eq!(game.take_turn("d8 h4".to_string()), Some(GameState::Check(Colour::White)));
+    }
+
+    #[test]
+    fn squares_between_on_a_rank() {
+        use crate::squares_between;
+
+        assert_eq!(squares_between("a1", "d1"), Some(vec!["b1".to_string(), "c1".to_string()]));
+        assert_eq!(squares_between("e4", "e5"), Some(vec![]));
+    }
+
+    #[test]
+    fn squares_between_on_a_diagonal() {
+        use crate::squares_between;
+
+        assert_eq!(squares_between("f6", "c3"), Some(vec!["e5".to_string(), "d4".to_string()]));
+    }
+
+    #[test]
+    fn squares_between_unaligned() {
+        use crate::squares_between;
+
+        assert_eq!(squares_between("a1", "b3"), None);
+        assert_eq!(squares_between("a1", "a1"), None);
+    }
+
+    #[test]
+    fn check_evasions_match_brute_force() {
+        use crate::Game;
+
+        let fens = [
+            "4k3/8/8/8/8/8/3q4/R3K2R w KQ - 0 1",
+            "4k3/8/8/8/1b6/8/8/RN2K1NR w KQ - 0 1",
+            "4k3/8/8/8/8/5n2/8/R2QK2R w KQ - 0 1",
+            "4k3/8/8/8/4r3/5n2/8/R2QK2R w KQ - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        ];
+        for fen in fens.iter() {
+            let mut game = Game::new_empty();
+            game.set_state_from_fen(fen);
+            assert!(game.is_king_threatened(game.current_turn), "{}", fen);
+            let mut fast = game.all_legal_moves();
+            let mut brute = game.get_all_moves();
+            fast.sort_by_key(|mov| (mov.from, mov.to));
+            brute.sort_by_key(|mov| (mov.from, mov.to));
+            assert_eq!(fast, brute, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn at_reads_board_coordinates() {
+        use crate::Game;
+        use crate::PieceKind;
+        use crate::Colour;
+
+        let game = Game::new();
+
+        assert_eq!(game.at(0, 0), Some((PieceKind::Rook, Colour::Black)));
+        assert_eq!(game.at(0, 7), Some((PieceKind::Rook, Colour::Black)));
+        assert_eq!(game.at(7, 0), Some((PieceKind::Rook, Colour::White)));
+        assert_eq!(game.at(7, 7), Some((PieceKind::Rook, Colour::White)));
+        assert_eq!(game.at(4, 4), None);
+    }
+
+    #[test]
+    fn colour_opposite() {
+        use crate::Colour;
+
+        assert_eq!(Colour::White.opposite(), Colour::Black);
+        assert_eq!(Colour::Black.opposite(), Colour::White);
+        assert!(Colour::White.is_white());
+        assert!(!Colour::Black.is_white());
+    }
+
+    #[test]
+    fn board_fen_is_first_fen_field() {
+        use crate::Game;
+
+        let game = Game::new();
+        assert_eq!(game.board_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
+    }
+
+    #[test]
+    fn pawns_on_back_ranks_do_not_panic() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("P3k2P/8/8/8/8/8/8/p3K2p w  - 0 1");
+        for &pos in [(0, 0), (0, 7), (7, 0), (7, 7)].iter() {
+            let piece = game.board[pos.0][pos.1];
+            let moves = piece.get_valid_moves(pos, &game.board, game.en_passant_square, game.castlings, game.current_turn);
+            assert!(moves.is_empty());
+        }
+        assert!(game.attackers_of("b2", false).is_empty());
+        assert!(game.attackers_of("b7", true).is_empty());
+        assert_eq!(game.all_legal_moves().len(), 5);
+    }
+
+    #[test]
+    fn pgn_collection_keeps_going_after_bad_game() {
+        use crate::parse_pgn_collection;
+        use crate::GameState;
+        use crate::PgnError;
+
+        let text = "[Event \"Paris\"]\n[White \"Morphy\"]\n[Black \"Duke Karl / Count Isouard\"]\n[Result \"1-0\"]\n\n\
+                    1. e4 e5 2. Nf3 d6 3. d4 Bg4 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7\n\
+                    8. Nc3 c6 9. Bg5 b5 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7\n\
+                    14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0\n\n\
+                    [Event \"Broken\"]\n[Result \"*\"]\n\n\
+                    1. e4 e5 2. Ke3 *\n";
+        let games = parse_pgn_collection(text);
+
+        assert_eq!(games.len(), 2);
+        let first = games[0].as_ref().unwrap();
+        assert_eq!(first.game_state(), GameState::Checkmate);
+        assert_eq!(first.history.len(), 33);
+        assert_eq!(games[1], Err(PgnError::Move(2)));
+    }
+
+    #[test]
+    fn pgn_from_fen_tag_with_promotion() {
+        use crate::Game;
+        use crate::PieceKind;
+
+        let pgn = "[FEN \"4k3/P7/8/8/8/8/7P/4K3 w - - 0 1\"]\n\n1. a8=N Kd7 2. Nb6+ *";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.get_fen(), "8/3k4/1N6/8/8/8/7P/4K3 b - - 2 2");
+        assert_eq!(game.white_promotion, PieceKind::Queen);
+    }
+
+    #[test]
+    fn terminal_kind_of_position() {
+        use crate::DrawReason;
+        use crate::Game;
+        use crate::TerminalKind;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b  - 0 1");
+        assert_eq!(game.terminal_kind(), Some(TerminalKind::Checkmate));
+
+        game.set_state_from_fen("7k/5Q2/6K1/8/8/8/8/8 b  - 0 1");
+        assert_eq!(game.terminal_kind(), Some(TerminalKind::Stalemate));
+
+        game.set_state_from_fen("4k3/8/8/8/8/8/8/4KB2 w  - 0 1");
+        assert_eq!(game.terminal_kind(), Some(TerminalKind::Draw(DrawReason::InsufficientMaterial)));
+
+        let mut game = Game::new();
+        assert_eq!(game.terminal_kind(), None);
+        game.apply_moves(&["g1 f3", "g8 f6", "f3 g1", "f6 g8", "g1 f3", "g8 f6", "f3 g1"]).unwrap();
+        assert_eq!(game.terminal_kind(), None);
+        game.apply_moves(&["f6 g8"]).unwrap();
+        assert_eq!(game.terminal_kind(), Some(TerminalKind::Draw(DrawReason::Threefold)));
+    }
+
+    #[test]
+    fn fen_with_wrong_dimensions_is_rejected() {
+        use crate::FenError;
+        use crate::Game;
+
+        assert_eq!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Err(FenError::Dimensions));
+        assert_eq!(Game::from_fen("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Err(FenError::Dimensions));
+        assert_eq!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8 w KQkq - 0 1"), Err(FenError::Dimensions));
+    }
+
+    #[test]
+    fn king_moves_of_cornered_king() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("7k/8/5K2/8/8/8/8/6R1 w  - 0 1");
+        assert_eq!(game.king_moves(false), vec!["h7".to_string()]);
+
+        game.set_state_from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        let mut moves = game.king_moves(true);
+        moves.sort();
+        assert_eq!(moves, vec!["c1", "d1", "d2", "e2", "f1", "f2", "g1"]);
+    }
+
+    #[test]
+    fn is_mate_move_before_playing() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w  - 0 1");
+        assert!(game.is_mate_move("a1", "a8", None));
+        assert!(!game.is_mate_move("a1", "a7", None));
+        assert_eq!(game.get_fen(), "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+
+        game.set_state_from_fen("6k1/P4ppp/8/8/8/8/8/6K1 w  - 0 1");
+        assert!(game.is_mate_move("a7", "a8", Some('q')));
+        assert!(!game.is_mate_move("a7", "a8", Some('n')));
+
+        game.set_state_from_fen("4rkr1/4p1p1/8/8/8/8/8/4K2R w K - 0 1");
+        assert!(game.is_mate_move("e1", "g1", None));
+        assert!(!game.is_mate_move("h1", "h8", None));
+    }
+
+    #[test]
+    fn pgn_skips_annotations() {
+        use crate::Game;
+
+        let pgn = "[Event \"Annotated\"]\n\n\
+                   1. e4 {The king's pawn.} 1... e5 $1 2.Nf3 (2. f4 exf4 (2... d5 {Falkbeer}) 3. Nf3) 2...Nc6 ; Defending e5\n\
+                   3. Bb5!? a6 $6 {A comment with (brackets) and ; a semicolon} 4. Ba4 *";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.get_fen(), "r1bqkbnr/1ppp1ppp/p1n5/4p3/B3P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 1 4");
+    }
+
+    #[test]
+    fn legal_destinations_follow_the_position() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert_eq!(game.legal_destinations("e2"), vec!["e3", "e4"]);
+        assert_eq!(game.legal_destinations("e2"), vec!["e3", "e4"]);
+        game.take_turn("e2 e4".to_string());
+        assert!(game.legal_destinations("e2").is_empty());
+        assert_eq!(game.legal_destinations("e7"), vec!["e6", "e5"]);
+        game.undo_move();
+        assert_eq!(game.legal_destinations("e2"), vec!["e3", "e4"]);
+
+        game.set_state_from_fen("4k3/P7/8/8/8/8/8/4K3 w  - 0 1");
+        assert_eq!(game.legal_destinations("a7"), vec!["a8"]);
+    }
+
+    #[test]
+    fn fen_rank_count_is_checked_first() {
+        use crate::FenError;
+        use crate::Game;
+
+        assert_eq!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/xyz w KQkq - 0 1"), Err(FenError::Dimensions));
+        assert_eq!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1"), Err(FenError::Piece('X')));
+    }
+
+    #[test]
+    fn can_force_mate_material_per_side() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/8/8/8/8/8/8/4KN2 w  - 0 1");
+        assert!(!game.can_force_mate_material(true));
+        assert!(!game.can_force_mate_material(false));
+
+        game.set_state_from_fen("4k3/8/8/8/8/8/8/R3K3 w  - 0 1");
+        assert!(game.can_force_mate_material(true));
+        assert!(!game.can_force_mate_material(false));
+
+        game.set_state_from_fen("4k3/8/8/8/8/8/8/2B1KB2 w  - 0 1");
+        assert!(game.can_force_mate_material(true));
+    }
+
+    #[test]
+    fn position_fens_in_order() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.apply_moves(&["e2 e4", "e7 e5", "g1 f3"]).unwrap();
+        assert_eq!(game.position_fens(), vec![
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+        ]);
+    }
+
+    #[test]
+    fn en_passant_exposing_king_on_rank_is_illegal() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("8/8/8/KPp4r/8/8/8/7k w  c6 0 1");
+        assert_eq!(game.legal_destinations("b5"), vec!["b6"]);
+        assert_eq!(game.try_take_turn("b5 c6"), Err(crate::MoveError::IllegalMove));
+
+        game.set_state_from_fen("8/8/8/1Pp4r/8/K7/8/7k w  c6 0 1");
+        assert!(game.legal_destinations("b5").contains(&"c6".to_string()));
+    }
+
+    #[test]
+    fn perft_reference_counts() {
+        use crate::Game;
+
+        let positions: [(&str, &[u64]); 6] = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902]),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]),
+            ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]),
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]),
+            ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079]),
+        ];
+        for (fen, counts) in positions.iter() {
+            let game = Game::from_fen(fen).unwrap();
+            for (depth, count) in counts.iter().enumerate() {
+                assert_eq!(game.perft(depth + 1), *count, "{} at depth {}", fen, depth + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn is_castling_move_sides() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(game.is_castling_move("e1", "g1"), Some(true));
+        assert_eq!(game.is_castling_move("e1", "c1"), Some(false));
+        assert_eq!(game.is_castling_move("e1", "d1"), None);
+        assert_eq!(game.is_castling_move("e8", "c8"), Some(false));
+        assert_eq!(game.is_castling_move("a1", "c1"), None);
+    }
+
+    #[test]
+    fn from_fen_loads_starting_position() {
+        use crate::FenError;
+        use crate::Game;
+        use crate::GameState;
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/4q3/4K3 w  - 0 40").unwrap();
+        assert_eq!(game.get_fen(), "4k3/8/8/8/8/8/4q3/4K3 w - - 0 40");
+        assert_eq!(game.game_state(), GameState::Check(crate::Colour::White));
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/4q3/4K3 x - - 0 40"), Err(FenError::ActiveColour));
+    }
+
+    #[test]
+    fn mobility_of_central_and_rim_knights() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/8/8/8/3N4/8/8/N3K3 b  - 0 1");
+        let mobility = game.mobility_map(true);
+        assert_eq!(mobility, vec![("d4".to_string(), 8), ("a1".to_string(), 2), ("e1".to_string(), 5)]);
+        assert_eq!(game.mobility_map(false), vec![("e8".to_string(), 5)]);
+    }
+
+    #[test]
+    fn passed_pawns_with_distance() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/2p5/8/8/1P5P/8/8/4K3 w  - 0 1");
+        assert_eq!(game.passed_pawns(true), vec![("h4".to_string(), 4)]);
+        assert_eq!(game.passed_pawns(false), Vec::<(String, usize)>::new());
+
+        game.set_state_from_fen("4k3/8/8/8/8/p7/8/4K3 w  - 0 1");
+        assert_eq!(game.passed_pawns(false), vec![("a3".to_string(), 2)]);
+    }
+
+    #[test]
+    fn threefold_in_fen_history() {
+        use crate::is_threefold;
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let knight_out = "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1";
+        let both_out = "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 2 2";
+        let white_back = "rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 3 2";
+        let history = [start, knight_out, both_out, white_back,
+                       "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3", knight_out, both_out, white_back];
+        assert!(!is_threefold(&history));
+        let mut history = history.to_vec();
+        history.push("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 8 5");
+        assert!(is_threefold(&history));
+    }
+
+    #[test]
+    fn pieces_that_can_move_to_square() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/8/8/8/8/5N2/8/1N5K w  - 0 1");
+        assert_eq!(game.pieces_that_can_move_to("d2"), vec!["f3", "b1"]);
+        assert_eq!(game.pieces_that_can_move_to("a3"), vec!["b1"]);
+        assert!(game.pieces_that_can_move_to("d5").is_empty());
+    }
+
+    #[test]
+    fn started_from_fen_only_for_custom_positions() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.take_turn("e2 e4".to_string());
+        assert_eq!(game.started_from_fen(), None);
+
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        game.take_turn("e2 e4".to_string());
+        assert_eq!(game.started_from_fen(), Some(fen.to_string()));
+        assert_eq!(game.clone_position().started_from_fen(), Some(game.get_fen()));
+        game.reset();
+        assert_eq!(game.started_from_fen(), None);
+
+        let game = Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 5 12").unwrap();
+        assert_eq!(game.started_from_fen(), None);
+    }
+
+    #[test]
+    fn en_passant_moves_name_the_captured_pawn() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/8/8/3Pp3/8/8/8/4K3 w  e6 0 1");
+        let moves = game.all_legal_moves();
+        let en_passant = moves.iter().find(|mov| mov.from == (3, 3) && mov.to == (2, 4)).unwrap();
+        assert_eq!(en_passant.ep_capture, Some((3, 4)));
+        let push = moves.iter().find(|mov| mov.from == (3, 3) && mov.to == (2, 3)).unwrap();
+        assert_eq!(push.ep_capture, None);
+    }
+
+    #[test]
+    fn generated_moves_are_classified() {
+        use crate::Game;
+        use crate::MoveKind;
+        use crate::PieceKind;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("1n2k3/P7/8/3Pp3/8/8/4P3/4K2R w K e6 0 1");
+        let moves = game.all_legal_moves();
+        let kind_of = |from: (usize, usize), to: (usize, usize), promotion: Option<PieceKind>| {
+            moves.iter().find(|mov| mov.from == from && mov.to == to && mov.promotion == promotion).unwrap().kind
+        };
+        assert_eq!(kind_of((7, 4), (7, 6), None), MoveKind::Castle(true));
+        assert_eq!(kind_of((3, 3), (2, 4), None), MoveKind::EnPassant);
+        assert_eq!(kind_of((1, 0), (0, 1), Some(PieceKind::Knight)), MoveKind::PromotionCapture(PieceKind::Knight));
+        assert_eq!(kind_of((1, 0), (0, 0), Some(PieceKind::Queen)), MoveKind::Promotion(PieceKind::Queen));
+        assert_eq!(kind_of((6, 4), (4, 4), None), MoveKind::DoublePawnPush);
+        assert_eq!(kind_of((6, 4), (5, 4), None), MoveKind::Quiet);
+    }
+
+    #[test]
+    fn checks_given_are_counted() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.apply_moves(&["e2 e4", "f7 f6", "d1 h5", "g7 g6", "h5 g6", "h7 g6"]).unwrap();
+        assert_eq!(game.check_count(true), 2);
+        assert_eq!(game.check_count(false), 0);
+        game.undo_move();
+        game.undo_move();
+        assert_eq!(game.check_count(true), 1);
+    }
+
+    #[test]
+    fn fen_move_counters_are_checked_and_clamped() {
+        use crate::FenError;
+        use crate::Game;
+
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1"), Err(FenError::Clock));
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - +3 1"), Err(FenError::Clock));
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 -1"), Err(FenError::MoveNumber));
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 123456789012").unwrap();
+        assert_eq!(game.turn, 123456789012);
+        let mut game_huge = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 99999999999999999999999999").unwrap();
+        assert_eq!(game_huge.turn, usize::MAX);
+        game.take_turn("e8 d8".to_string());
+        game_huge.take_turn("e8 d8".to_string());
+        assert_eq!(game.turn, 123456789013);
+        assert_eq!(game_huge.turn, usize::MAX);
+    }
+
+    #[test]
+    fn forced_move_detection() {
+        use crate::Game;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("7k/8/6K1/8/8/8/8/R7 b  - 0 1");
+        assert!(game.is_forced());
+        game.set_state_from_fen("7k/8/8/8/8/8/8/R5RK b  - 0 1");
+        assert!(game.is_forced());
+        game.set_state_from_fen("7k/P7/8/8/8/8/8/7K w  - 0 1");
+        assert!(!game.is_forced());
+        assert!(!Game::new().is_forced());
+    }
+
+    #[test]
+    fn game_phase_from_material() {
+        use crate::Game;
+        use crate::Phase;
+
+        let mut game = Game::new();
+        assert_eq!(game.phase_value(), 0);
+        assert_eq!(game.game_phase(), Phase::Opening);
+
+        game.set_state_from_fen("r3k3/pp6/8/8/8/8/PP6/2Q1KB2 w  - 0 30");
+        assert_eq!(game.game_phase(), Phase::Middlegame);
+
+        game.set_state_from_fen("4k3/8/8/8/8/8/4P3/4K3 w  - 0 60");
+        assert_eq!(game.phase_value(), 256);
+        assert_eq!(game.game_phase(), Phase::Endgame);
+    }
+
+    #[test]
+    fn move_callback_fires_once_per_legal_move() {
+        use crate::Game;
+        use crate::MoveOutcome;
+        use std::sync::{Arc, Mutex};
+
+        let outcomes: Arc<Mutex<Vec<MoveOutcome>>> = Arc::new(Mutex::new(Vec::new()));
+        let mut game = Game::new();
+        let seen = Arc::clone(&outcomes);
+        game.set_on_move(move |outcome| seen.lock().unwrap().push(outcome.clone()));
+
+        game.take_turn("e2 e4".to_string());
+        game.take_turn("e2 e4".to_string());
+        game.take_turn("e7 e4".to_string());
+        game.take_turn("e7 e5".to_string());
+        assert_eq!(outcomes.lock().unwrap().len(), 2);
+        assert_eq!(outcomes.lock().unwrap()[1].to, "e5");
+
+        assert!(game.apply_moves(&["g1 f3", "a1 a2"]).is_err());
+        assert_eq!(outcomes.lock().unwrap().len(), 2);
+        game.apply_moves(&["g1 f3", "b8 c6"]).unwrap();
+        assert_eq!(outcomes.lock().unwrap().len(), 4);
+
+        game.reset();
+        game.take_turn("e2 e4".to_string());
+        assert_eq!(outcomes.lock().unwrap().len(), 4);
+        assert_eq!(Arc::strong_count(&outcomes), 1);
+    }
+
+    #[test]
+    fn weak_squares_are_attacked_more_than_defended() {
+        use crate::Game;
+
+        let game = Game::from_fen("3rk3/8/8/8/8/2N5/8/4K3 w - - 0 1").unwrap();
+        let white = game.weak_squares(true);
+        assert!(white.contains(&"d4".to_string()));
+        assert!(white.contains(&"d3".to_string()));
+        assert!(!white.contains(&"d2".to_string()));
+        assert!(!white.contains(&"d1".to_string()));
+        assert!(!white.contains(&"d5".to_string()));
+
+        let black = game.weak_squares(false);
+        assert!(black.contains(&"b5".to_string()));
+        assert!(!black.contains(&"d5".to_string()));
+    }
+
+    #[test]
+    fn make_move_promotes_to_the_piece_named_by_the_move() {
+        use crate::{Colour, Game, Piece, PieceKind};
+
+        let mut game = Game::from_fen("8/1P5k/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        game.set_default_promotion(true, PieceKind::Knight);
+        let promotions = game.all_legal_moves().into_iter().filter(|mov| mov.promotion.is_some()).collect::<Vec<_>>();
+        assert_eq!(promotions.len(), 4);
+        for mov in promotions {
+            let mut next = game.clone();
+            next.make_move(mov.from, mov.to, mov.promotion);
+            let expected = match mov.promotion.unwrap() {
+                PieceKind::Queen => Piece::Queen(Colour::White),
+                PieceKind::Rook => Piece::Rook(Colour::White),
+                PieceKind::Bishop => Piece::Bishop(Colour::White),
+                _ => Piece::Knight(Colour::White),
+            };
+            assert_eq!(next.board[0][1], expected);
+            assert_eq!(next.history.last().unwrap().promotion, mov.promotion);
+            assert_eq!(next.white_promotion, PieceKind::Knight);
+        }
+    }
+
+    #[test]
+    fn undoing_castling_puts_king_and_rook_back() {
+        use crate::{Colour, Game, Piece};
+
+        let start = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut game = start.clone();
+        game.take_turn("e1 g1".to_string());
+        let record = *game.history.last().unwrap();
+        assert_eq!(record.castling_rook, Some(((7, 7), (7, 5))));
+        assert_eq!(record.castling_san(), Some("O-O"));
+        game.take_turn("e8 c8".to_string());
+        assert_eq!(game.history.last().unwrap().castling_san(), Some("O-O-O"));
+        assert_eq!(game.board[0][3], Piece::Rook(Colour::Black));
+
+        game.undo_move();
+        game.undo_move();
+        assert_eq!(game.board, start.board);
+        assert_eq!(game.castlings, (true, true, true, true));
+        assert_eq!(game.get_fen(), start.get_fen());
+
+        game.take_turn("a1 a2".to_string());
+        assert_eq!(game.history.last().unwrap().castling_san(), None);
+    }
+
+    #[test]
+    fn legal_moves_uci_lists_start_position_moves() {
+        use crate::Game;
+
+        let moves = Game::new().legal_moves_uci();
+        assert_eq!(moves.len(), 20);
+        for mov in ["g1f3", "g1h3", "b1a3", "b1c3", "e2e4", "a2a3"].iter() {
+            assert!(moves.contains(&mov.to_string()));
+        }
+
+        let promotions = Game::from_fen("8/4P2k/8/8/8/8/8/4K2R w K - 0 1").unwrap().legal_moves_uci();
+        for mov in ["e7e8q", "e7e8r", "e7e8b", "e7e8n", "e1g1"].iter() {
+            assert!(promotions.contains(&mov.to_string()));
+        }
+    }
+
+    #[test]
+    fn seventy_five_move_rule_takes_precedence_over_insufficient_material() {
+        use crate::{DrawReason, Game, GameState};
+
+        let game = Game::from_fen("8/8/8/4k3/8/8/3NK3/8 w - - 150 80").unwrap();
+        assert_eq!(game.game_state(), GameState::Draw(DrawReason::SeventyFiveMove));
+
+        let game = Game::from_fen("8/8/8/4k3/8/8/3NK3/8 w - - 10 80").unwrap();
+        assert_eq!(game.game_state(), GameState::Draw(DrawReason::InsufficientMaterial));
+        assert_eq!(game.result(), "1/2-1/2");
+
+        let mut game = Game::from_fen("k7/8/1Q6/8/8/8/8/7K w - - 99 80").unwrap();
+        assert_eq!(game.take_turn("b6 c7".to_string()), Some(GameState::Stalemate));
+    }
+
+    #[test]
+    fn check_escapes_capture_block_or_move_the_king() {
+        use crate::{square_name, Game};
+
+        let game = Game::from_fen("7k/8/8/4r3/2N5/R7/8/4K3 w - - 0 1").unwrap();
+        let mut escapes = game.check_escapes().into_iter()
+            .map(|mov| square_name(mov.from) + &square_name(mov.to))
+            .collect::<Vec<String>>();
+        escapes.sort();
+        assert_eq!(escapes, vec!["a3e3", "c4e3", "c4e5", "e1d1", "e1d2", "e1f1", "e1f2"]);
+
+        assert!(Game::new().check_escapes().is_empty());
+        assert!(Game::from_fen("7k/8/8/8/8/8/5PPP/r5K1 w - - 0 1").unwrap().check_escapes().is_empty());
+    }
+
+    #[test]
+    fn short_fen_leaves_out_the_clocks() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert_eq!(game.get_fen_short(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -");
+        game.take_turn("e2 e4".to_string());
+        assert_eq!(game.get_fen_short(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3");
+        assert!(game.get_fen().starts_with(&game.get_fen_short()));
+    }
+
+    #[test]
+    fn pin_direction_points_along_the_pin() {
+        use crate::Game;
+
+        let game = Game::from_fen("4r2k/8/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.pin_direction("e2"), Some((-1, 0)));
+        assert_eq!(game.pin_direction("e1"), None);
+        assert_eq!(game.pin_direction("e4"), None);
+
+        let game = Game::from_fen("7k/6n1/8/8/3B4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.pin_direction("g7"), Some((1, -1)));
+    }
+
+    #[test]
+    fn en_passant_square_from_fen_clears_after_a_knight_move() {
+        use crate::Game;
+
+        let mut game = Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(game.en_passant_square, (5, 4));
+        game.take_turn("g8 f6".to_string()).unwrap();
+        assert_eq!(game.en_passant_square, (8, 8));
+        assert_eq!(game.get_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2");
+    }
+
+    #[test]
+    fn attack_lines_follow_a_rook_up_to_the_first_blocker() {
+        use crate::Game;
+
+        let game = Game::from_fen("7k/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut rook = game.attack_lines(true).into_iter()
+            .filter(|(from, _)| from == "a1")
+            .map(|(_, to)| to)
+            .collect::<Vec<String>>();
+        rook.sort();
+        assert_eq!(rook, vec!["a2", "a3", "a4", "a5", "a6", "a7", "a8", "b1", "c1", "d1", "e1"]);
+        assert_eq!(game.attack_lines(true).len(), 16);
+        assert!(game.attack_lines(false).iter().all(|(from, _)| from == "h8"));
+    }
+
+    #[test]
+    fn has_legal_material_rejects_impossible_piece_counts() {
+        use crate::Game;
+
+        assert!(Game::new().has_legal_material());
+        assert!(!Game::from_fen("4k3/8/8/8/8/PPPPPPPP/PP6/4K3 w - - 0 1").unwrap().has_legal_material());
+        assert!(!Game::from_fen("4k3/8/8/8/8/QQ6/PPPPPPPP/3QK3 w - - 0 1").unwrap().has_legal_material());
+        assert!(Game::from_fen("4k3/8/8/8/8/QQ6/PPPPPP2/3QK3 w - - 0 1").unwrap().has_legal_material());
+        assert!(!Game::from_fen("4k3/8/8/8/8/8/8/4K2k w - - 0 1").unwrap().has_legal_material());
+    }
+
+    #[test]
+    fn evaluate_is_balanced_at_the_start() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert_eq!(game.evaluate(), 0);
+        game.take_turn("e2 e4".to_string());
+        assert!(game.evaluate() > 0);
+        assert_eq!(game.mirror().evaluate(), -game.evaluate());
+
+        let game = Game::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert!(game.evaluate() > 800);
+    }
+
+    #[test]
+    fn resolve_san_finds_the_move_without_playing_it() {
+        use crate::{Game, MoveError, MoveKind};
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        let mov = game.resolve_san("Kd2").unwrap();
+        assert_eq!((mov.from, mov.to), ((7, 4), (6, 3)));
+        assert_eq!(game.resolve_san("O-O").unwrap().kind, MoveKind::Castle(true));
+        assert_eq!(game.resolve_san("Nf3"), Err(MoveError::IllegalMove));
+        assert_eq!(game.resolve_san("R"), Err(MoveError::Notation));
+        assert_eq!(game.get_fen(), "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+
+        let rooks = Game::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+        assert_eq!(rooks.resolve_san("Rd1"), Err(MoveError::Ambiguous));
+        assert_eq!(rooks.resolve_san("Rad1").unwrap().from, (7, 0));
+
+        let mut game = game;
+        assert!(game.take_turn_san("O-O-O+").is_ok());
+        assert_eq!(game.board_fen(), "4k3/8/8/8/8/8/8/2KR3R");
+    }
+
+    #[test]
+    fn occupancy_counts_sixteen_pieces_each_at_the_start() {
+        use crate::Game;
+
+        let (white, black) = Game::new().occupancy();
+        assert_eq!(white.count_ones(), 16);
+        assert_eq!(black.count_ones(), 16);
+        assert_eq!(white, 0xFFFF);
+        assert_eq!(black, 0xFFFF << 48);
+
+        let (white, black) = Game::from_fen("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap().occupancy();
+        assert_eq!((white, black), (1, 1 << 63));
+    }
+
+    #[test]
+    fn piece_bitboards_match_the_start_position() {
+        use crate::{Colour, Game, PieceKind};
+
+        let game = Game::new();
+        assert_eq!(game.piece_bitboard(PieceKind::Pawn, Colour::White), 0xFF00);
+        assert_eq!(game.piece_bitboard(PieceKind::Pawn, Colour::Black), 0xFF << 48);
+        assert_eq!(game.piece_bitboard(PieceKind::King, Colour::White), 1 << 4);
+        assert_eq!(game.piece_bitboard(PieceKind::Rook, Colour::Black), (1 << 56) | (1 << 63));
+        assert_eq!(game.piece_bitboard(PieceKind::Knight, Colour::White).count_ones(), 2);
+    }
+
+    #[test]
+    fn goto_ply_steps_backward_and_forward_through_history() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.apply_moves(&["e2 e4", "e7 e5", "g1 f3", "b8 c6", "f1 c4"]).unwrap();
+        let after_five = game.get_fen();
+        let mut after_two = Game::new();
+        after_two.apply_moves(&["e2 e4", "e7 e5"]).unwrap();
+
+        game.goto_ply(2).unwrap();
+        assert_eq!(game.get_fen(), after_two.get_fen());
+        game.goto_ply(0).unwrap();
+        assert_eq!(game.get_fen(), Game::new().get_fen());
+        game.goto_ply(5).unwrap();
+        assert_eq!(game.get_fen(), after_five);
+        assert_eq!(game.goto_ply(6), Err(()));
+
+        game.goto_ply(2).unwrap();
+        game.take_turn("d2 d4".to_string()).unwrap();
+        assert_eq!(game.goto_ply(4), Err(()));
+    }
+
+    #[test]
+    fn search_repetition_is_found_by_zobrist_hash() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        let mut stack = vec![game.zobrist_hash()];
+        for mov in ["g1 f3", "g8 f6", "f3 g1"].iter() {
+            game.take_turn(mov.to_string()).unwrap();
+            assert!(!game.is_repetition_in_search(&stack));
+            stack.push(game.zobrist_hash());
+        }
+        game.take_turn("f6 g8".to_string()).unwrap();
+        assert!(game.is_repetition_in_search(&stack));
+        assert_eq!(game.zobrist_hash(), Game::new().zobrist_hash());
+
+        let mut pushed = Game::new();
+        pushed.take_turn("e2 e4".to_string()).unwrap();
+        let without_en_passant = Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_ne!(pushed.zobrist_hash(), without_en_passant.zobrist_hash());
+    }
+
+    #[test]
+    fn from_fen_and_moves_plays_from_the_given_position() {
+        use crate::{FenError, Game, MoveError, PieceKind};
+
+        let game = Game::from_fen_and_moves("4k3/1P6/8/8/8/8/7P/4K3 w - - 0 1", &["b7b8n", "e8 e7"]).unwrap();
+        assert_eq!(game.board_fen(), "1N6/4k3/8/8/8/8/7P/4K3");
+        assert_eq!(game.turn, 2);
+        assert_eq!(game.history[0].promotion, Some(PieceKind::Knight));
+
+        assert_eq!(Game::from_fen_and_moves("4k3/8/8/8/8/8/7P/4K3 w - - 0 1", &["e1e2", "e2e3"]).err(), Some(MoveError::WrongColour));
+        assert_eq!(Game::from_fen_and_moves("4k3/8/8/8/8/8/8/4K3 w - - 0 1", &["e1e9"]).err(), Some(MoveError::Notation));
+        assert_eq!(Game::from_fen_and_moves("4k3/8/8 w - - 0 1", &[]).err(), Some(MoveError::Fen(FenError::Dimensions)));
+    }
+
+    #[test]
+    fn trivial_draw_endgames_are_recognised_by_material() {
+        use crate::Game;
+
+        let draw = |fen: &str| Game::from_fen(fen).unwrap().is_trivial_draw_endgame();
+        assert!(draw("4k3/8/2b5/8/8/8/3B4/4K3 w - - 0 1"));
+        assert!(!draw("4k3/8/3b4/8/8/8/3B4/4K3 w - - 0 1"));
+        assert!(draw("4k3/8/3r4/8/8/8/3R4/4K3 w - - 0 1"));
+        assert!(draw("4k3/8/3b4/8/8/8/3R4/4K3 w - - 0 1"));
+        assert!(draw("4k3/8/3n4/8/8/8/3N4/4K3 w - - 0 1"));
+        assert!(!draw("4k3/8/8/8/8/8/3R4/4K3 w - - 0 1"));
+        assert!(!draw("4k3/8/3r4/8/8/8/3RP3/4K3 w - - 0 1"));
+        assert!(!draw("4k3/8/8/8/8/8/3RR3/4K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn unusual_promotion_kinds_fall_back_to_a_queen() {
+        use crate::{Colour, Game, Piece, PieceKind};
+
+        for kind in [PieceKind::King, PieceKind::Pawn].iter() {
+            let mut game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+            game.white_promotion = *kind;
+            assert!(game.take_turn("b7 b8".to_string()).is_some());
+            assert_eq!(game.board[0][1], Piece::Queen(Colour::White));
+        }
+    }
+
+    #[test]
+    fn checking_moves_include_discovered_checks() {
+        use crate::{square_name, Game};
+
+        let game = Game::from_fen("7k/6pp/8/8/8/8/8/K3R3 w - - 0 1").unwrap();
+        let checks = game.checking_moves().into_iter()
+            .map(|mov| square_name(mov.from) + &square_name(mov.to))
+            .collect::<Vec<String>>();
+        assert_eq!(checks, vec!["e1e8"]);
+
+        let game = Game::from_fen("7k/8/8/8/7N/8/8/K6R w - - 0 1").unwrap();
+        let mut checks = game.checking_moves().into_iter()
+            .map(|mov| square_name(mov.from) + &square_name(mov.to))
+            .collect::<Vec<String>>();
+        checks.sort();
+        assert_eq!(checks, vec!["h4f3", "h4f5", "h4g2", "h4g6"]);
+    }
+
+    #[test]
+    fn moves_until_fifty_move_draw_counts_full_moves() {
+        use crate::Game;
+
+        assert_eq!(Game::new().moves_until_fifty_move_draw(), 50);
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 96 70").unwrap();
+        assert_eq!(game.moves_until_fifty_move_draw(), 2);
+        game.take_turn("a1 a2".to_string());
+        assert_eq!(game.moves_until_fifty_move_draw(), 2);
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 120 70").unwrap().moves_until_fifty_move_draw(), 0);
+    }
+
+    #[test]
+    fn diff_lists_the_squares_a_move_changes() {
+        use crate::Game;
+
+        let before = Game::new();
+        let mut after = before.clone();
+        after.take_turn("g1 f3".to_string());
+        assert_eq!(before.diff(&after), vec![
+            ("f3".to_string(), None, Some('N')),
+            ("g1".to_string(), Some('N'), None),
+        ]);
+        assert!(after.diff(&after).is_empty());
+
+        let mut castled = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let start = castled.clone();
+        castled.take_turn("e1 g1".to_string());
+        assert_eq!(start.diff(&castled).len(), 4);
+    }
+
+    #[test]
+    fn crazyhouse_captures_go_to_hand_and_can_be_dropped() {
+        use crate::{Colour, Game, MoveError, Piece, PieceKind, Variant};
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1").unwrap();
+        game.variant = Variant::Crazyhouse;
+        game.take_turn("e1 d2".to_string()).unwrap();
+        assert_eq!(game.hand(true), vec![PieceKind::Pawn]);
+        assert!(game.hand(false).is_empty());
+        assert_eq!(game.game_state(), crate::GameState::InProgress);
+        game.take_turn("e8 e7".to_string()).unwrap();
+
+        assert_eq!(game.try_take_turn("P@a8"), Err(MoveError::IllegalMove));
+        assert_eq!(game.try_take_turn("P@a1"), Err(MoveError::IllegalMove));
+        assert_eq!(game.try_take_turn("N@e5"), Err(MoveError::NoPiece));
+        assert_eq!(game.try_take_turn("P@e9"), Err(MoveError::Notation));
+        game.try_take_turn("P@e5").unwrap();
+        assert_eq!(game.board[3][4], Piece::Pawn(Colour::White));
+        assert!(game.hand(true).is_empty());
+
+        game.undo_move();
+        assert_eq!(game.board[3][4], Piece::Empty);
+        assert_eq!(game.hand(true), vec![PieceKind::Pawn]);
+        game.undo_move();
+        game.undo_move();
+        assert!(game.hand(true).is_empty());
+        assert_eq!(game.board_fen(), "4k3/8/8/8/8/8/3p4/4K3");
+    }
+
+    #[test]
+    fn standard_games_have_no_hands_or_drops() {
+        use crate::{Game, MoveError};
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/3p3P/4K3 w - - 0 1").unwrap();
+        game.take_turn("e1 d2".to_string()).unwrap();
+        game.take_turn("e8 e7".to_string()).unwrap();
+        assert!(game.hand(true).is_empty());
+        assert_eq!(game.try_take_turn("P@e5"), Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn sanitize_castling_rights_clears_rights_without_a_rook() {
+        use crate::Game;
+
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K3 w KQkq - 0 1").unwrap();
+        game.sanitize_castling_rights();
+        assert_eq!(game.castlings, (false, true, true, true));
+        assert!(!game.king_moves(true).contains(&"g1".to_string()));
+
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R4K1R w KQkq - 0 1").unwrap();
+        game.sanitize_castling_rights();
+        assert_eq!(game.castlings, (false, false, true, true));
+    }
+
+    #[test]
+    fn is_en_passant_only_for_the_capture_onto_the_target() {
+        use crate::Game;
+
+        let game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert!(game.is_en_passant("e5", "d6"));
+        assert!(!game.is_en_passant("e5", "e6"));
+        assert!(!game.is_en_passant("e1", "d2"));
+
+        let game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(!game.is_en_passant("e5", "d6"));
+    }
+
+    #[test]
+    fn coverage_splits_defended_and_attacked_squares() {
+        use crate::Game;
+
+        let game = Game::from_fen("4k3/8/8/8/8/P7/8/R3K3 w - - 0 1").unwrap();
+        let (defended, attacked) = game.coverage("a1");
+        assert_eq!(defended, vec!["a3", "e1"]);
+        assert_eq!(attacked, vec!["a2", "b1", "c1", "d1"]);
+        assert!(!attacked.contains(&"a4".to_string()));
+
+        let (defended, attacked) = game.coverage("a3");
+        assert!(defended.is_empty());
+        assert_eq!(attacked, vec!["b4"]);
+        assert_eq!(game.coverage("h5"), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn ordered_legal_moves_put_the_best_captures_first() {
+        use crate::{square_name, Game};
+
+        let game = Game::from_fen("4k3/8/8/3q1p2/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let moves = game.ordered_legal_moves().into_iter()
+            .map(|mov| square_name(mov.from) + &square_name(mov.to))
+            .collect::<Vec<String>>();
+        assert_eq!(moves[..2], ["e4d5".to_string(), "e4f5".to_string()]);
+        let push = moves.iter().position(|mov| mov == "e4e5").unwrap();
+        assert!(push > 1);
+        assert_eq!(moves.len(), game.all_legal_moves().len());
+        assert_eq!(game.ordered_legal_moves(), game.ordered_legal_moves());
+    }
+
+    #[test]
+    fn smothered_mate_is_told_apart_from_other_mates() {
+        use crate::Game;
+
+        assert!(Game::from_fen("6rk/5Npp/8/8/8/8/8/6K1 b - - 0 1").unwrap().is_smothered_mate());
+        assert!(!Game::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap().is_smothered_mate());
+        assert!(!Game::from_fen("6rk/6pp/8/6N1/8/8/8/6K1 b - - 0 1").unwrap().is_smothered_mate());
+        assert!(!Game::new().is_smothered_mate());
+    }
+
+    #[test]
+    fn from_ascii_reads_a_diagram() {
+        use crate::{Game, PositionError};
+
+        let art = "
+....k...
+....p...
+........
+........
+.....N..
+........
+
+....K...
+";
+        let game = Game::from_ascii(art, true).unwrap();
+        assert_eq!(game.board_fen(), "4k3/4p3/8/8/5N2/8/8/4K3");
+        assert_eq!(game.castlings, (false, false, false, false));
+
+        assert_eq!(Game::from_ascii("....k...\n", true).err(), Some(PositionError::Dimensions));
+        assert_eq!(Game::from_ascii(&art.replace('N', "X"), true).err(), Some(PositionError::Piece('X')));
+    }
+
+    #[test]
+    fn castling_blocked_reason_names_the_problem() {
+        use crate::Game;
+
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/5r2/R3K1NR w KQkq - 0 1").unwrap();
+        assert_eq!(game.castling_blocked_reason(true, true), Some("g1 is occupied".to_string()));
+        assert_eq!(game.castling_blocked_reason(true, false), None);
+
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(game.castling_blocked_reason(true, true), None);
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3KR2 w Qkq - 0 1").unwrap();
+        assert_eq!(game.castling_blocked_reason(true, true), Some("no rook on h1".to_string()));
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R4K1R w - - 0 1").unwrap();
+        assert_eq!(game.castling_blocked_reason(true, false), Some("king has moved".to_string()));
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQq - 0 1").unwrap();
+        assert_eq!(game.castling_blocked_reason(false, true), Some("castling right has been lost".to_string()));
+
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R2rK2R w KQkq - 0 1").unwrap();
+        assert_eq!(game.castling_blocked_reason(true, true), Some("king is in check".to_string()));
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        assert_eq!(game.castling_blocked_reason(false, true), None);
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3KR1R b KQkq - 0 1").unwrap();
+        assert_eq!(game.castling_blocked_reason(false, true), Some("f8 is attacked".to_string()));
+    }
+
+    #[test]
+    fn legal_moves_iter_matches_all_legal_moves() {
+        use crate::Game;
+
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/1P6/8/3pP3/8/8/8/4K2R w K d6 0 1",
+        ];
+        for fen in fens.iter() {
+            let game = Game::from_fen(fen).unwrap();
+            let mut lazy = game.legal_moves_iter().map(|mov| (mov.from, mov.to, mov.promotion)).collect::<Vec<_>>();
+            let mut eager = game.all_legal_moves().into_iter().map(|mov| (mov.from, mov.to, mov.promotion)).collect::<Vec<_>>();
+            lazy.sort_by_key(|mov| (mov.0, mov.1, format!("{:?}", mov.2)));
+            eager.sort_by_key(|mov| (mov.0, mov.1, format!("{:?}", mov.2)));
+            assert_eq!(lazy, eager, "{}", fen);
+        }
+        assert!(Game::new().legal_moves_iter().next().is_some());
+    }
+
+    #[test]
+    fn claimable_draws_need_a_claim_but_automatic_ones_dont() {
+        use crate::{DrawReason, Game, GameState};
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert_eq!(game.take_turn("a1 a2".to_string()), Some(GameState::InProgress));
+        assert_eq!(game.claimable_draw(), Some(DrawReason::FiftyMove));
+
+        let mut game = Game::new();
+        assert_eq!(game.claimable_draw(), None);
+        assert_eq!(game.claim_draw(), None);
+        let shuffle = ["g1 f3", "g8 f6", "f3 g1", "f6 g8"];
+        for _ in 0..2 {
+            game.apply_moves(&shuffle).unwrap();
+        }
+        assert_eq!(game.game_state(), GameState::InProgress);
+        assert_eq!(game.claimable_draw(), Some(DrawReason::Threefold));
+        for _ in 0..2 {
+            game.apply_moves(&shuffle).unwrap();
+        }
+        assert_eq!(game.game_state(), GameState::Draw(DrawReason::Fivefold));
+    }
+
+    #[test]
+    fn last_promotion_reports_square_and_piece() {
+        use crate::Game;
+
+        let mut game = Game::from_fen("8/4P3/8/8/8/K6k/1p6/8 w - - 0 1").unwrap();
+        assert_eq!(game.last_promotion(), None);
+        game.take_turn_san("e8=N").unwrap();
+        assert_eq!(game.last_promotion(), Some(("e8".to_string(), 'N')));
+        game.take_turn_san("b1=N").unwrap();
+        assert_eq!(game.last_promotion(), Some(("b1".to_string(), 'n')));
+        game.take_turn_san("Kb3").unwrap();
+        assert_eq!(game.last_promotion(), None);
+    }
+
+    #[test]
+    fn timed_search_returns_legal_move_within_budget() {
+        use crate::{search_best_move_timed, Game};
+        use std::time::Instant;
+
+        let game = Game::new();
+        let start = Instant::now();
+        let mov = search_best_move_timed(&game, 200).unwrap();
+        assert!(start.elapsed().as_millis() < 2000);
+        assert!(game.all_legal_moves().contains(&mov));
+
+        // Finds the back-rank mate in one.
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mov = search_best_move_timed(&game, 2000).unwrap();
+        assert_eq!((mov.from, mov.to), ((7, 0), (0, 0)));
+
+        let game = Game::from_fen("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(search_best_move_timed(&game, 100), None);
+    }
+
+    #[test]
+    fn legal_placements_exclude_back_ranks_for_pawns() {
+        use crate::Game;
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let pawn = game.legal_placements('P');
+        // Every square from the second to the seventh rank, except d7 and f7, where a White pawn would give check out of turn.
+        assert_eq!(pawn.len(), 46);
+        assert!(pawn.iter().all(|square| !square.ends_with('1') && !square.ends_with('8')));
+
+        let knight = game.legal_placements('N');
+        assert!(!knight.contains(&"e1".to_string()) && !knight.contains(&"e8".to_string()));
+        // A White knight on d6 would attack the Black king while it is White's turn.
+        assert!(!knight.contains(&"d6".to_string()));
+        assert!(knight.contains(&"d3".to_string()));
+
+        assert!(game.legal_placements('K').is_empty());
+        assert!(game.legal_placements('x').is_empty());
+    }
+
+    #[test]
+    fn same_position_ignores_clocks() {
+        use crate::Game;
+
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 12 30").unwrap();
+        assert!(game.same_position_as("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 3 30"));
+        assert!(game.same_position_as("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"));
+        assert!(!game.same_position_as("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 12 30"));
+        assert!(!game.same_position_as("r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 12 30"));
+        assert!(!game.same_position_as("not a fen"));
+    }
+
+    #[test]
+    fn castling_field_is_dash_once_all_rights_are_lost() {
+        use crate::Game;
+
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        game.take_turn("e1 e2".to_string()).unwrap();
+        assert_eq!(game.get_fen().split(' ').nth(2), Some("kq"));
+        game.take_turn("e8 e7".to_string()).unwrap();
+        let fen = game.get_fen();
+        assert_eq!(fen.split(' ').nth(2), Some("-"));
+        assert_eq!(fen, "r6r/4k3/8/8/8/8/4K3/R6R w - - 2 2");
+        assert_eq!(Game::from_fen(&fen).unwrap().get_fen(), fen);
+    }
+
+    #[test]
+    fn intact_pawn_shield_scores_higher() {
+        use crate::Game;
+
+        let intact = Game::from_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        let advanced = Game::from_fen("6k1/5ppp/8/8/8/6PP/5P2/6K1 w - - 0 1").unwrap();
+        let missing = Game::from_fen("6k1/5ppp/8/8/8/8/5P2/6K1 w - - 0 1").unwrap();
+        assert_eq!(intact.king_shield(true), 30);
+        assert!(intact.king_shield(true) > advanced.king_shield(true));
+        assert!(advanced.king_shield(true) > missing.king_shield(true));
+        assert_eq!(intact.king_shield(true), intact.king_shield(false));
+    }
+
+    #[test]
+    fn capture_that_opens_the_way_creates_passed_pawn() {
+        use crate::Game;
+
+        let game = Game::from_fen("4k3/8/1p6/P7/3B4/8/8/4K3 w - - 0 1").unwrap();
+        assert!(game.creates_passed_pawn("d4", "b6"));
+        assert!(!game.creates_passed_pawn("e1", "e2"));
+        assert!(!game.creates_passed_pawn("d4", "a7"));
+        assert!(!game.creates_passed_pawn("d4", "d8"));
+
+        // The pawn on a5 is already passed, so pushing it doesn't create one.
+        let game = Game::from_fen("4k3/8/8/P7/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(!game.creates_passed_pawn("a5", "a6"));
+    }
+
+    #[test]
+    fn fen_with_turn_flips_side_and_clears_en_passant() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        game.take_turn("e2 e4".to_string()).unwrap();
+        assert_eq!(game.get_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        assert_eq!(game.fen_with_turn(true), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(game.fen_with_turn(false), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        assert_eq!(game.get_fen().split(' ').nth(3), Some("e3"));
+    }
+
+    #[test]
+    fn knight_forking_king_and_queen_is_reported() {
+        use crate::Game;
+
+        let game = Game::from_fen("q3k3/2N5/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(game.forks(true), vec![("c7".to_string(), vec!["a8".to_string(), "e8".to_string()])]);
+        assert!(game.forks(false).is_empty());
+
+        // Two defended pawns aren't worth a knight, so attacking both is no fork.
+        let game = Game::from_fen("4k3/1p6/p3p3/3p4/1N6/8/8/4K3 w - - 0 1").unwrap();
+        assert!(game.forks(true).is_empty());
+        let game = Game::from_fen("4k3/1p6/p7/3p4/1N6/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.forks(true), vec![("b4".to_string(), vec!["a6".to_string(), "d5".to_string()])]);
+    }
+
+    #[test]
+    fn lichess_path_replaces_spaces() {
+        use crate::Game;
+
+        assert_eq!(Game::new().to_lichess_path(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR_w_KQkq_-_0_1");
+    }
+
+    #[test]
+    fn zugzwang_lite_in_trebuchet() {
+        use crate::Game;
+
+        // Whoever is to move has to let their pawn go.
+        let game = Game::from_fen("8/8/8/2Kp4/3Pk3/8/8/8 w - - 0 1").unwrap();
+        assert!(game.is_in_zugzwang_lite());
+        let game = Game::from_fen("8/8/8/2Kp4/3Pk3/8/8/8 b - - 0 1").unwrap();
+        assert!(game.is_in_zugzwang_lite());
+
+        let mut game = Game::new();
+        game.apply_moves(&["e2 e4", "e7 e5", "g1 f3", "b8 c6"]).unwrap();
+        assert!(!game.is_in_zugzwang_lite());
+    }
+
+    #[test]
+    fn legal_moves_in_fen_lists_knight_destinations() {
+        use crate::{legal_moves_in_fen, FenError};
+
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut moves = legal_moves_in_fen(fen, "f3").unwrap();
+        moves.sort();
+        assert_eq!(moves, ["d4", "e5", "g1", "g5", "h4"]);
+        let mut moves = legal_moves_in_fen(fen, "c6").unwrap();
+        moves.sort();
+        assert_eq!(moves, ["a5", "b4", "b8", "d4", "e7"]);
+        assert!(legal_moves_in_fen(fen, "e3").unwrap().is_empty());
+        assert!(matches!(legal_moves_in_fen("not a fen", "f3"), Err(FenError::FieldCount)));
+    }
+
+    #[test]
+    fn control_map_of_start_position_is_symmetric() {
+        use crate::Game;
+
+        let map = Game::new().control_map();
+        for &(x, y) in [(3, 3), (3, 4), (4, 3), (4, 4)].iter() {
+            assert_eq!(map[x][y], 0);
+        }
+        // f3 is covered by the pawns on e2 and g2 and the knight on g1, and f6 likewise by Black.
+        assert_eq!(map[5][5], 3);
+        assert_eq!(map[2][5], -3);
+        for x in 0..8 {
+            assert_eq!(map[x].map(|control| -control), map[7 - x]);
+        }
+    }
+
+    #[test]
+    fn winning_captures_skip_equal_trades() {
+        use crate::Game;
+
+        // The knight on a5 is free, while taking the one on e4 is answered by the pawn on f5.
+        let game = Game::from_fen("4k3/8/8/n4p2/4n3/2N5/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(game.legal_captures().len(), 2);
+        let winning = game.winning_captures();
+        assert_eq!(winning.len(), 1);
+        assert_eq!((winning[0].from, winning[0].to), ((7, 0), (3, 0)));
+    }
+
+    #[test]
+    fn epd_lists_operations_after_position() {
+        use crate::Game;
+
+        let game = Game::new();
+        assert_eq!(
+            game.to_epd(&[("bm", "e4"), ("id", "\"test\"")]),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4; id \"test\";"
+        );
+        assert_eq!(game.to_epd(&[]), game.get_fen_short());
+    }
+
+    #[test]
+    fn epd_parses_position_and_operations() {
+        use crate::{FenError, Game};
+
+        let epd = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5; id \"Ruy; test\"; hmvc 2;";
+        let (game, ops) = Game::from_epd(epd).unwrap();
+        assert_eq!(game.get_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 1");
+        assert_eq!(ops[0], ("bm".to_string(), "Bb5".to_string()));
+        assert_eq!(ops[1], ("id".to_string(), "\"Ruy; test\"".to_string()));
+        assert_eq!(ops.len(), 3);
+
+        let (game, ops) = Game::from_epd(&Game::new().to_epd(&[("bm", "e4")])).unwrap();
+        assert_eq!(game.get_fen(), Game::new().get_fen());
+        assert_eq!(ops, vec![("bm".to_string(), "e4".to_string())]);
+        assert!(matches!(Game::from_epd("8/8/8/8/8/8/8/8 w"), Err(FenError::FieldCount)));
+    }
+
+    #[test]
+    fn eco_code_recognises_sicilian() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert_eq!(game.eco_code(), None);
+        game.apply_moves(&["e2 e4", "c7 c5"]).unwrap();
+        assert_eq!(game.eco_code(), Some(("B20".to_string(), "Sicilian Defence".to_string())));
+        // Leaving the table keeps the last opening recognised.
+        game.apply_moves(&["b1 c3", "b8 c6"]).unwrap();
+        assert_eq!(game.eco_code(), Some(("B20".to_string(), "Sicilian Defence".to_string())));
+
+        // Transpositions reach the same entry.
+        let mut game = Game::new();
+        game.apply_moves(&["c2 c4", "e7 e6", "d2 d4", "d7 d5"]).unwrap();
+        assert_eq!(game.eco_code().unwrap().0, "D30");
+    }
+
+    #[test]
+    fn must_promote_when_king_is_stuck() {
+        use crate::Game;
+
+        let game = Game::from_fen("8/P7/8/8/8/8/5k2/5n1K w - - 0 1").unwrap();
+        assert_eq!(game.all_legal_moves().len(), 4);
+        assert!(game.must_promote());
+        assert!(!Game::new().must_promote());
+
+        let game = Game::from_fen("8/P7/8/8/8/8/5k2/7K w - - 0 1").unwrap();
+        assert!(!game.must_promote());
+    }
+
+    #[test]
+    fn restore_returns_to_savepoint() {
+        use crate::{DrawReason, Game};
+
+        let mut game = Game::new();
+        game.apply_moves(&["g1 f3", "g8 f6", "f3 g1", "f6 g8", "g1 f3", "g8 f6", "f3 g1"]).unwrap();
+        let saved = game.clone();
+        let snap = game.savepoint();
+
+        game.apply_moves(&["f6 g8", "e2 e4", "e7 e5"]).unwrap();
+        game.undo_move();
+        assert_ne!(game, saved);
+
+        game.restore(snap.clone());
+        assert_eq!(game, saved);
+        assert_eq!(game.history.len(), 7);
+        // The repetitions before the savepoint still count.
+        game.take_turn("f6 g8".to_string()).unwrap();
+        assert_eq!(game.claimable_draw(), Some(DrawReason::Threefold));
+
+        game.restore(snap);
+        assert_eq!(game, saved);
+    }
+
+    #[test]
+    fn material_breakdown_after_knight_for_bishop() {
+        use crate::{Game, PieceKind};
+
+        let mut game = Game::new();
+        assert!(game.material_breakdown().iter().all(|(_, difference)| *difference == 0));
+        game.apply_moves(&["e2 e4", "e7 e5", "g1 f3", "b8 c6", "f1 b5", "a7 a6", "b5 c6", "d7 c6"]).unwrap();
+        assert_eq!(game.material_breakdown(), vec![
+            (PieceKind::Queen, 0),
+            (PieceKind::Rook, 0),
+            (PieceKind::Bishop, -1),
+            (PieceKind::Knight, 1),
+            (PieceKind::Pawn, 0),
+        ]);
+    }
+
+    #[test]
+    fn position_seen_before_after_knight_shuffle() {
+        use crate::Game;
+
+        let mut game = Game::new();
+        assert!(!game.position_seen_before());
+        game.apply_moves(&["g1 f3", "g8 f6", "f3 g1"]).unwrap();
+        assert!(!game.position_seen_before());
+        game.take_turn("f6 g8".to_string()).unwrap();
+        assert!(game.position_seen_before());
+        game.take_turn("e2 e4".to_string()).unwrap();
+        assert!(!game.position_seen_before());
+    }
+
+    #[test]
+    fn custom_promotion_policy_is_honoured() {
+        use crate::{Game, PieceKind, PromotionPolicy};
+
+        struct KnightsOnly;
+
+        impl PromotionPolicy for KnightsOnly {
+            fn choose(&self, _colour_is_white: bool, _from: &str, _to: &str, _requested: PieceKind) -> PieceKind {
+                PieceKind::Knight
+            }
+        }
+
+        let mut game = Game::from_fen("8/4P3/8/8/8/K6k/8/8 w - - 0 1").unwrap();
+        game.set_promotion_policy(KnightsOnly);
+        game.take_turn("e7 e8".to_string()).unwrap();
+        assert_eq!(game.board_fen(), "4N3/8/8/8/8/K6k/8/8");
+        assert_eq!(game.last_promotion(), Some(("e8".to_string(), 'N')));
+
+        let mut game = Game::from_fen("8/4P3/8/8/8/K6k/8/8 w - - 0 1").unwrap();
+        game.take_turn("e7 e8".to_string()).unwrap();
+        assert_eq!(game.board_fen(), "4Q3/8/8/8/8/K6k/8/8");
+    }
+
+    #[test]
+    fn capturing_last_piece_leaves_only_kings() {
+        use crate::{DrawReason, Game, GameState};
+
+        let mut game = Game::from_fen("7k/8/8/8/3r4/3K4/8/8 w - - 0 40").unwrap();
+        assert!(!game.only_kings());
+        assert_eq!(game.take_turn("d3 d4".to_string()), Some(GameState::Draw(DrawReason::InsufficientMaterial)));
+        assert!(game.only_kings());
+        assert_eq!(game.result(), "1/2-1/2");
+    }
+
+    #[test]
+    fn mobility_difference_matches_mobility_maps() {
+        use crate::Game;
+
+        assert_eq!(Game::new().mobility_difference(), 0);
+
+        let total = |game: &Game, colour_is_white: bool| game.mobility_map(colour_is_white).iter().map(|(_, count)| *count as i32).sum::<i32>();
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+            "4k3/8/8/8/8/8/4q3/4K3 w - - 0 40",
+            "8/P7/8/8/8/8/5k2/5n1K w - - 0 1",
+        ].iter() {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.mobility_difference(), total(&game, true) - total(&game, false), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn lossy_fen_keeps_readable_parts() {
+        use crate::{FenError, Game};
+
+        let (game, errors) = Game::from_fen_lossy("rnbqkbnr/pppppppp/8/8/4X3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        assert_eq!(errors, vec![FenError::Piece('X')]);
+        assert_eq!(game.board_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPP1PPP/RNBQKBNR");
+        assert_eq!(game.get_fen().split(' ').nth(1), Some("b"));
+
+        let (game, errors) = Game::from_fen_lossy("4k3/8/8/8/8/8/8/4K3 x KQkq");
+        assert_eq!(errors, vec![FenError::FieldCount, FenError::ActiveColour, FenError::EnPassant, FenError::Clock, FenError::MoveNumber]);
+        assert_eq!(game.get_fen(), "4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1");
+
+        let (game, errors) = Game::from_fen_lossy(&Game::new().get_fen());
+        assert!(errors.is_empty());
+        assert_eq!(game, Game::new());
+    }
+
+    #[test]
+    fn king_distance_counts_king_moves() {
+        use crate::Game;
+
+        assert_eq!(Game::new().king_distance(), 7);
+        assert_eq!(Game::from_fen("8/8/8/3k4/4K3/8/8/8 w - - 0 1").unwrap().king_distance(), 1);
+        assert_eq!(Game::from_fen("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap().king_distance(), 7);
+        assert_eq!(Game::from_fen("8/8/8/8/8/8/8/K7 w - - 0 1").unwrap().king_distance(), 0);
+    }
+
+    #[test]
+    fn special_move_kind_classifies_special_rules() {
+        use crate::{Game, MoveKind, PieceKind};
+
+        let game = Game::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        assert_eq!(game.special_move_kind("e5", "d6"), Some(MoveKind::EnPassant));
+        assert_eq!(game.special_move_kind("e1", "g1"), Some(MoveKind::Castle(true)));
+        assert_eq!(game.special_move_kind("e1", "c1"), Some(MoveKind::Castle(false)));
+        assert_eq!(game.special_move_kind("b7", "b8"), Some(MoveKind::Promotion(PieceKind::Queen)));
+        assert_eq!(game.special_move_kind("b7", "a8"), Some(MoveKind::PromotionCapture(PieceKind::Queen)));
+        assert_eq!(game.special_move_kind("e5", "e6"), None);
+        assert_eq!(game.special_move_kind("e1", "e3"), None);
+    }
+
+    test!{
+        name: bishop_on_a1_moves_along_long_diagonal,
+        fen: "8/8/8/8/8/8/8/B7 w - - 0 0",
+        piece: a1,
+        legal_moves: [b2, c3, d4, e5, f6, g7, h8],
+    }
+
+    test!{
+        name: bishop_on_a8_moves_along_long_diagonal,
+        fen: "B7/8/8/8/8/8/8/8 w - - 0 0",
+        piece: a8,
+        legal_moves: [b7, c6, d5, e4, f3, g2, h1],
+    }
+
+    test!{
+        name: bishop_on_h1_moves_along_long_diagonal,
+        fen: "8/8/8/8/8/8/8/7B w - - 0 0",
+        piece: h1,
+        legal_moves: [g2, f3, e4, d5, c6, b7, a8],
+    }
+
+    test!{
+        name: bishop_on_h8_stops_at_pieces,
+        fen: "7b/8/8/8/3P4/8/8/8 b - - 0 0",
+        piece: h8,
+        legal_moves: [g7, f6, e5, d4],
+    }
+
+    #[test]
+    fn seventy_five_move_rule_draws_without_a_claim() {
+        use crate::{DrawReason, Game, GameState};
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K2R w - - 148 100").unwrap();
+        assert_eq!(game.take_turn("a1 a2".to_string()), Some(GameState::InProgress));
+        assert_eq!(game.claimable_draw(), Some(DrawReason::FiftyMove));
+        assert_eq!(game.take_turn("e8 d8".to_string()), Some(GameState::Draw(DrawReason::SeventyFiveMove)));
+        assert_eq!(game.result(), "1/2-1/2");
+
+        // A capture on the 150th half-move resets the clock instead.
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/r7/R3K3 w - - 149 100").unwrap();
+        assert_eq!(game.take_turn("a1 a2".to_string()), Some(GameState::InProgress));
+    }
+
+    #[test]
+    fn safe_moves_exclude_hanging_the_queen() {
+        use crate::Game;
+
+        let game = Game::from_fen("4k3/8/8/3p4/8/8/Q7/4K3 w - - 0 1").unwrap();
+        let safe = game.safe_moves().into_iter().map(|mov| (mov.from, mov.to)).collect::<Vec<_>>();
+        // Qc4 can be taken by the pawn on d5, while Qb3 and Qxd5 are safe.
+        assert!(!safe.contains(&((6, 0), (4, 2))));
+        assert!(safe.contains(&((6, 0), (5, 1))));
+        assert!(safe.contains(&((6, 0), (3, 3))));
+        assert!(safe.len() < game.all_legal_moves().len());
+    }
+
+    #[test]
+    fn malformed_squares_are_rejected_without_panicking() {
+        use crate::{Game, MoveError};
+
+        assert!(Game::is_valid_square("e4"));
+        assert!(Game::is_valid_square("h8"));
+        for square in ["z9", "e", "e4 ", "E4", "e0", "e44", ""].iter() {
+            assert!(!Game::is_valid_square(square), "{:?}", square);
+        }
+
+        let mut game = Game::new();
+        for mov in ["z9 e4", "e e4", "e2 e4 ", "e2", "", "e2  e4"].iter() {
+            assert_eq!(game.try_take_turn(mov), Err(MoveError::MalformedInput), "{:?}", mov);
+        }
+        assert_eq!(game.take_turn("e4 ".to_string()), None);
+        assert_eq!(game, Game::new());
+
+        assert!(game.legal_destinations("z9").is_empty());
+        assert!(!game.is_en_passant("e", "d6"));
+        assert_eq!(game.special_move_kind("e1", "g9"), None);
+        assert!(game.take_turn("e2 e4".to_string()).is_some());
+    }
+
+    #[test]
+    fn white_pawn_on_seventh_rank_can_move() {
+        use crate::Game;
+        use crate::Piece;
+        use crate::Colour;
+
+        let mut game = Game::new_empty();
+        game.set_state_from_fen("4k3/P7/8/8/8/8/8/4K3 w  - 0 1");
+
+        assert!(game.take_turn("a7 a8".to_string()).is_some());
+        assert_eq!(game.board[0][0], Piece::Queen(Colour::White));
+        assert_eq!(game.board[1][0], Piece::Empty);
+    }
+
+    #[test]
+    fn moves_are_refused_after_timeout() {
+        use crate::Game;
+        use crate::MoveError;
+
+        let mut game = Game::new();
+        game.set_time_control(1_000, 1_000, 0);
+        game.take_turn("e2 e4".to_string());
+        game.record_move_time(2_000);
+
+        assert_eq!(game.try_take_turn("e7 e5"), Err(MoveError::GameOver));
+        assert_eq!(game.result(), "0-1");
+        assert_eq!(game.board[1][4], crate::Piece::Pawn(crate::Colour::Black));
+    }
+
+    #[test]
+    fn move_time_keeps_checkmate() {
+        use crate::Game;
+        use crate::GameState;
+
+        let mut game = Game::new();
+        game.set_time_control(60_000, 1_000, 0);
+        game.take_turn("f2 f3".to_string());
+        game.take_turn("e7 e5".to_string());
+        game.take_turn("g2 g4".to_string());
+        game.take_turn("d8 h4".to_string());
+
+        assert_eq!(game.record_move_time(2_000), Some(GameState::Checkmate));
+        assert_eq!(game.result(), "0-1");
+    }
+
+    #[test]
+    fn resignation_survives_further_moves() {
+        use crate::Game;
+        use crate::MoveError;
+
+        let mut game = Game::new();
+        game.resign(true);
+
+        assert_eq!(game.take_turn("e2 e4".to_string()), None);
+        assert_eq!(game.try_take_turn("e2 e4"), Err(MoveError::GameOver));
+        assert_eq!(game.result(), "0-1");
+    }
+
+    #[test]
+    fn legal_moves_in_fen_rejects_malformed_square() {
+        use crate::legal_moves_in_fen;
+
+        let fen = "4k3/8/8/8/8/5N2/8/4K3 w - - 0 1";
+        assert_eq!(legal_moves_in_fen(fen, "z9"), Ok(Vec::new()));
+        assert_eq!(legal_moves_in_fen(fen, "f"), Ok(Vec::new()));
+        assert_eq!(legal_moves_in_fen(fen, ""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn square_queries_reject_malformed_squares() {
+        use crate::{squares_between, Game, MoveError};
+
+        let game = Game::new();
+        assert_eq!(game.is_castling_move("e1", "z1"), None);
+        assert!(game.pieces_that_can_move_to("e9").is_empty());
+        assert_eq!(game.pin_direction("E2"), None);
+        assert!(game.attackers_of("", true).is_empty());
+        assert_eq!(game.coverage("b"), (Vec::new(), Vec::new()));
+        assert!(!game.is_defended("e22"));
+        assert_eq!(squares_between("a1", "i9"), None);
+        assert_eq!(Game::from_fen_and_moves("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", &["e2z4"]).err(), Some(MoveError::Notation));
+        assert_eq!(Game::from_fen_and_moves("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", &["e2E4"]).err(), Some(MoveError::Notation));
+    }
+
+    #[test]
+    fn position_key_matches_transposition_in_more_moves() {
+        use crate::Game;
+        use std::collections::HashMap;
+
+        let mut seen = HashMap::new();
+        let mut game = Game::new();
+        game.take_turn("e2 e4".to_string());
+        game.take_turn("e7 e5".to_string());
+        game.take_turn("g1 f3".to_string());
+        seen.insert(game.position_key(), "direct");
+
+        let mut longer = Game::new();
+        longer.take_turn("g1 f3".to_string());
+        longer.take_turn("g8 f6".to_string());
+        longer.take_turn("f3 g1".to_string());
+        longer.take_turn("f6 g8".to_string());
+        longer.take_turn("e2 e4".to_string());
+        longer.take_turn("e7 e5".to_string());
+        longer.take_turn("g1 f3".to_string());
+
+        assert_ne!(game, longer);
+        assert_eq!(seen.get(&longer.position_key()), Some(&"direct"));
+        longer.take_turn("b8 c6".to_string());
+        assert_eq!(seen.get(&longer.position_key()), None);
+    }
+
+    #[test]
+    fn total_plies_counts_moves_played_not_move_number() {
+        use crate::Game;
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 40").unwrap();
+        assert_eq!(game.total_plies(), 0);
+        game.take_turn("e8 d7".to_string());
+        game.take_turn("e2 e4".to_string());
+        assert_eq!(game.total_plies(), 2);
+    }
+
+    #[test]
+    fn crazyhouse_promoted_pieces_go_to_hand_as_pawns() {
+        use crate::{Colour, Game, Piece, PieceKind, Variant};
+
+        let mut game = Game::from_fen("3rk3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        game.variant = Variant::Crazyhouse;
+        game.take_turn("a7 a8".to_string()).unwrap();
+        game.take_turn("e8 e7".to_string()).unwrap();
+        game.take_turn("a8 d8".to_string()).unwrap();
+        assert_eq!(game.hand(true), vec![PieceKind::Rook]);
+        game.take_turn("e7 d8".to_string()).unwrap();
+        assert_eq!(game.hand(false), vec![PieceKind::Pawn]);
+
+        game.undo_move();
+        assert!(game.hand(false).is_empty());
+        assert_eq!(game.board[0][3], Piece::Queen(Colour::White));
+        game.take_turn("e7 d8".to_string()).unwrap();
+        assert_eq!(game.hand(false), vec![PieceKind::Pawn]);
+    }
+
+    #[test]
+    fn resolve_san_promotes_to_default_piece_without_one_given() {
+        use crate::{Game, PieceKind};
+
+        let mut game = Game::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.resolve_san("b8").unwrap().promotion, Some(PieceKind::Queen));
+        assert_eq!(game.resolve_san("bxa8").unwrap().promotion, Some(PieceKind::Queen));
+        assert_eq!(game.resolve_san("b8=R").unwrap().promotion, Some(PieceKind::Rook));
+
+        game.set_default_promotion(true, PieceKind::Knight);
+        assert_eq!(game.resolve_san("bxa8").unwrap().promotion, Some(PieceKind::Knight));
+    }
+
+    #[test]
+    fn repetitions_follow_undo_and_irreversible_moves() {
+        use crate::{DrawReason, Game};
+
+        let mut game = Game::new();
+        game.apply_moves(&["g1 f3", "g8 f6", "f3 g1", "f6 g8", "g1 f3", "g8 f6", "f3 g1", "f6 g8"]).unwrap();
+        assert_eq!(game.claimable_draw(), Some(DrawReason::Threefold));
+        game.undo_move();
+        assert_eq!(game.claimable_draw(), None);
+        game.take_turn("f6 g8".to_string()).unwrap();
+        assert_eq!(game.claimable_draw(), Some(DrawReason::Threefold));
+
+        game.apply_moves(&["e2 e4", "e7 e5", "g1 f3", "g8 f6", "f3 g1", "f6 g8", "g1 f3", "g8 f6", "f3 g1", "f6 g8"]).unwrap();
+        assert!(game.position_seen_before());
+        assert_eq!(game.claimable_draw(), None);
+    }
+
+    #[test]
+    fn king_moves_match_brute_force() {
+        use crate::Game;
+
+        let fens = [
+            "4k3/8/8/8/8/8/8/r3K3 w - - 0 1",
+            "4k3/8/8/8/8/2p5/3p4/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/4K3/2b1r3 w - - 0 1",
+            "4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1",
+        ];
+        for fen in fens.iter() {
+            let game = Game::from_fen(fen).unwrap();
+            let king = game.find_king(game.current_turn).unwrap();
+            let mut fast = game.safe_king_moves(king);
+            let mut brute = game.moves_from(king);
+            fast.sort();
+            brute.sort();
+            assert_eq!(fast, brute, "{}", fen);
+        }
+        assert!(!Game::from_fen(fens[0]).unwrap().safe_king_moves((7, 4)).contains(&(7, 5)));
+    }
+
+    #[test]
+    fn loading_a_finished_position_sets_its_state() {
+        use crate::{DrawReason, Game, GameState, MoveError};
+
+        let mut game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.game_state(), GameState::Stalemate);
+        assert_eq!(game.try_take_turn("h8 g8"), Err(MoveError::GameOver));
+
+        game.set_state_from_fen("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(game.game_state(), GameState::Checkmate);
+        game.set_state_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(game.game_state(), GameState::Draw(DrawReason::InsufficientMaterial));
+    }
+
+    #[test]
+    fn game_is_send_and_sync_with_a_callback() {
+        use crate::Game;
+
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let mut game = Game::new();
+        game.set_on_move(|_| ());
+        assert_send_sync(&game);
+    }
+
+    #[test]
+    fn loading_a_fen_clears_hands_and_clocks() {
+        use crate::{Game, PieceKind, Variant};
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/3p3P/4K3 w - - 0 1").unwrap();
+        game.variant = Variant::Crazyhouse;
+        game.set_time_control(60_000, 60_000, 0);
+        game.take_turn("e1 d2".to_string()).unwrap();
+        assert_eq!(game.hand(true), vec![PieceKind::Pawn]);
+
+        game.set_state_from_fen("4k3/8/8/8/8/8/7P/4K3 w - - 0 1");
+        assert!(game.hand(true).is_empty());
+        assert_eq!(game.time_remaining(true), None);
+    }
+}
+
+#[cfg(test)]
+mod game_record_tests {
+    use crate::Game;
+    use crate::GameState;
+    use crate::DrawReason;
+
+    /// Plays a game given as a space-separated list of UCI moves, such as "e2e4 e7e5 e1g1 a7a8q",
+    /// failing the test on the first move that is rejected.
+    fn play_uci(game: &mut Game, moves: &str) {
+        for (idx, mov) in moves.split_whitespace().enumerate() {
+            if let Some(promotion) = mov.chars().nth(4) {
+                game.select_promotion(promotion);
+            }
+            let result = game.try_take_turn(&format!("{} {}", &mov[0..2], &mov[2..4]));
+            assert!(result.is_ok(), "move {} ({}) was rejected: {:?}", idx, mov, result);
+        }
+    }
+
+    #[test]
+    fn morphy_opera_game() {
+        let mut game = Game::new();
+        play_uci(&mut game, "e2e4 e7e5 g1f3 d7d6 d2d4 c8g4 d4e5 g4f3 d1f3 d6e5 f1c4 g8f6 f3b3 d8e7 \
+                             b1c3 c7c6 c1g5 b7b5 c3b5 c6b5 c4b5 b8d7 e1c1 a8d8 d1d7 d8d7 h1d1 e7e6 \
+                             b5d7 f6d7 b3b8 d7b8 d1d8");
+
+        assert_eq!(game.game_state(), GameState::Checkmate);
+        assert_eq!(game.result(), "1-0");
+    }
+
+    #[test]
+    fn lasker_thomas_king_hunt() {
+        let mut game = Game::new();
+        play_uci(&mut game, "d2d4 e7e6 g1f3 f7f5 b1c3 g8f6 c1g5 f8e7 g5f6 e7f6 e2e4 f5e4 c3e4 b7b6 \
+                             f3e5 e8g8 f1d3 c8b7 d1h5 d8e7 h5h7 g8h7 e4f6 h7h6 e5g4 h6g5 h2h4 g5f4 \
+                             g2g3 f4f3 d3e2 f3g2 h1h2 g2g1 e1d2");
+
+        assert_eq!(game.game_state(), GameState::Checkmate);
+        assert_eq!(game.result(), "1-0");
+    }
+
+    #[test]
+    fn berlin_draw_by_repetition() {
+        let mut game = Game::new();
+        play_uci(&mut game, "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6 e1g1 f6e4 f1e1 e4d6 f3e5 f8e7 b5f1 c6e5 \
+                             e1e5 e8g8");
+
+        let mut positions = Vec::new();
+        for mov in "e5e1 d6e8 e1e2 e8d6 e2e1 d6e8 e1e2 e8d6 e2e1".split_whitespace() {
+            play_uci(&mut game, mov);
+            positions.push(game.get_fen().split(' ').take(4).collect::<Vec<&str>>().join(" "));
+        }
+        let repetitions = positions.iter().filter(|position| *position == positions.last().unwrap()).count();
+
+        assert_eq!(repetitions, 3);
+        assert_eq!(game.game_state(), GameState::InProgress);
+        assert_eq!(game.claimable_draw(), Some(DrawReason::Threefold));
+        assert_eq!(game.result(), "*");
+
+        assert_eq!(game.claim_draw(), Some(GameState::Draw(DrawReason::Threefold)));
+        assert_eq!(game.result(), "1/2-1/2");
+    }
+}